@@ -0,0 +1,173 @@
+use aocutils::point::Point;
+use eframe::egui::{Pos2, Rect, Vec2};
+
+// How the warehouse canvas is looked at. Fit squeezes the whole map into the
+// window, Free is wherever the mouse wheel and dragging left it, and Follow keeps
+// the robot in the middle at the current zoom.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Fit,
+    Free,
+    Follow,
+}
+
+// zoom is relative to Fit
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub mode: CameraMode,
+    pub zoom: f32,
+    // where the map's top left goes, from the canvas's top left, when Free
+    pub offset: Vec2,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            mode: CameraMode::Fit,
+            zoom: 1.0,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+// Where the map is drawn this frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    // the map's top left
+    pub origin: Pos2,
+    // one cell across
+    pub spacing: f32,
+}
+
+impl View {
+    pub fn cell_rect(&self, pos: Point<usize>) -> Rect {
+        Rect::from_min_size(
+            self.origin + Vec2::new(pos.x as f32, pos.y as f32) * self.spacing,
+            Vec2::splat(self.spacing),
+        )
+    }
+
+    // The cell under a point on screen, None above or left of the map
+    pub fn cell_at(&self, screen: Pos2) -> Option<Point<usize>> {
+        let cell = (screen - self.origin) / self.spacing;
+        (cell.x >= 0.0 && cell.y >= 0.0).then_some(Point {
+            x: cell.x as usize,
+            y: cell.y as usize,
+        })
+    }
+}
+
+impl Camera {
+    // The map, width by height cells, in the canvas rect
+    pub fn view(&self, rect: Rect, width: usize, height: usize, robot: Point<usize>) -> View {
+        // max(1) so an empty warehouse doesn't divide by zero
+        let fit = (rect.width() / width.max(1) as f32).min(rect.height() / height.max(1) as f32);
+        match self.mode {
+            CameraMode::Fit => View {
+                origin: rect.min,
+                spacing: fit,
+            },
+            CameraMode::Free => View {
+                origin: rect.min + self.offset,
+                spacing: fit * self.zoom,
+            },
+            CameraMode::Follow => {
+                let spacing = fit * self.zoom;
+                let robot = Vec2::new(robot.x as f32 + 0.5, robot.y as f32 + 0.5);
+                View {
+                    origin: rect.center() - robot * spacing,
+                    spacing,
+                }
+            }
+        }
+    }
+
+    // Zoom by factor keeping whatever's under the pointer there. Following stays
+    // on the robot.
+    pub fn zoom_at(&mut self, view: View, rect: Rect, pointer: Pos2, factor: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        if self.mode != CameraMode::Follow {
+            let cell = (pointer - view.origin) / view.spacing;
+            let spacing = view.spacing * zoom / self.zoom;
+            self.offset = pointer - cell * spacing - rect.min;
+            self.mode = CameraMode::Free;
+        }
+        self.zoom = zoom;
+    }
+
+    // Dragging always leaves Fit or Follow
+    pub fn pan(&mut self, view: View, rect: Rect, delta: Vec2) {
+        self.offset = view.origin - rect.min + delta;
+        self.mode = CameraMode::Free;
+    }
+
+    pub fn fit(&mut self) {
+        *self = Camera::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{Camera, CameraMode};
+    use aocutils::point::Point;
+    use eframe::egui::{Pos2, Rect, Vec2};
+
+    const ROBOT: Point<usize> = Point { x: 4, y: 2 };
+
+    fn rect() -> Rect {
+        Rect::from_min_size(Pos2::new(10.0, 20.0), Vec2::new(500.0, 250.0))
+    }
+
+    #[test]
+    fn test_fit() {
+        let camera = Camera::default();
+        // 10x5 cells, 50 across each way
+        let view = camera.view(rect(), 10, 5, ROBOT);
+        assert_eq!(view.spacing, 50.0);
+        assert_eq!(
+            view.cell_rect(Point { x: 1, y: 2 }).min,
+            Pos2::new(60.0, 120.0)
+        );
+        assert_eq!(
+            view.cell_at(Pos2::new(99.0, 121.0)),
+            Some(Point { x: 1, y: 2 })
+        );
+        assert_eq!(view.cell_at(Pos2::new(9.0, 121.0)), None);
+    }
+
+    #[test]
+    fn test_zoom_keeps_pointer_cell() {
+        let mut camera = Camera::default();
+        let view = camera.view(rect(), 10, 5, ROBOT);
+        let pointer = Pos2::new(185.0, 145.0);
+        let cell = view.cell_at(pointer);
+        camera.zoom_at(view, rect(), pointer, 4.0);
+        assert_eq!(camera.mode, CameraMode::Free);
+        let zoomed = camera.view(rect(), 10, 5, ROBOT);
+        assert_eq!(zoomed.spacing, 200.0);
+        assert_eq!(zoomed.cell_at(pointer), cell);
+
+        // Never past the limits
+        camera.zoom_at(zoomed, rect(), pointer, 1000.0);
+        assert_eq!(camera.zoom, 50.0);
+    }
+
+    #[test]
+    fn test_pan_and_follow() {
+        let mut camera = Camera::default();
+        let view = camera.view(rect(), 10, 5, ROBOT);
+        camera.pan(view, rect(), Vec2::new(-30.0, 5.0));
+        let panned = camera.view(rect(), 10, 5, ROBOT);
+        assert_eq!(panned.origin, Pos2::new(-20.0, 25.0));
+
+        camera.mode = CameraMode::Follow;
+        camera.zoom = 2.0;
+        let view = camera.view(rect(), 10, 5, ROBOT);
+        assert_eq!(view.cell_rect(ROBOT).center(), rect().center());
+    }
+}
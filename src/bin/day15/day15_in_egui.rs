@@ -1,189 +1,654 @@
 // hide console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use crate::camera::{Camera, CameraMode};
+use crate::editor::{Editor, Tool};
+use crate::history::History;
+use crate::metrics::Metrics;
+use crate::warehouse::{gps, Direction, MoveOutcome, Warehouse};
 use aocutils::point::Point;
-use eframe::egui::{self, Pos2};
-use eframe::emath::Vec2;
-use std::collections::{HashMap, VecDeque};
+use eframe::egui::{self, RichText};
 use std::fs;
+use std::path::Path;
 
-pub fn egui_main() -> Result<(), eframe::Error> {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Mode {
+    // through the instructions from the file
+    #[default]
+    Replay,
+    // the arrow keys drive the robot, recording as it goes
+    Play,
+    // painting a new map
+    Edit,
+}
+
+pub fn egui_main(file: &str) -> Result<(), eframe::Error> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 800.0]),
         ..Default::default()
     };
+    let file = file.to_string();
     eframe::run_native(
-        "AoC2024 - Day 15.1",
+        "AoC2024 - Day 15",
         options,
         Box::new(|cc| {
             // This gives us image support:
             egui_extras::install_image_loaders(&cc.egui_ctx);
             // run the main loop
-            Ok(Box::new(Warehouse::new(cc)))
+            Ok(Box::new(WarehouseApp::new(cc, file)))
         }),
     )
 }
 
-// The model, this is the warehouse from the puzzle code
-#[derive(Debug, Clone, Default)]
-struct Robot {
-    pos: Point<usize>,
-    moved_successfully: bool,
-}
-
-#[derive(Debug, Clone)]
-enum Direction {
-    North,
-    East,
-    South,
-    West,
-}
-
-#[derive(Debug, Clone)]
-enum Obstacle {
-    Wall,
-    Box,
-}
-
+// The model, the shared warehouse plus what the app needs to replay it
 #[derive(Default, Debug, Clone)]
-struct Warehouse {
-    max_x: usize,
-    max_y: usize,
-    original_robot: Robot,
-    robot: Robot,
-    original_locations: HashMap<Point<usize>, Obstacle>,
-    locations: HashMap<Point<usize>, Obstacle>,
-    instructions: Vec<Direction>,
-    instruction_queue: VecDeque<Direction>,
-    iterations: usize,
+struct WarehouseApp {
+    file: String,
+    // part two, boxes two cells wide
+    wide: bool,
+    // what went wrong loading the file, if anything
+    load_error: Option<String>,
+    original: Warehouse,
+    warehouse: Warehouse,
+    // every move made so far, so the timeline can go back as well as forward
+    history: History,
+    mode: Mode,
+    // where play started from, what an export's map is
+    play_start: Warehouse,
+    // the last export or copy
+    play_message: String,
+    editor: Editor,
+    tool: Tool,
+    camera: Camera,
+    // kept up with history for the metrics panel
+    metrics: Metrics,
+    show_metrics: bool,
+    // the last save, or why it failed
+    edit_message: String,
     delay: f64,
     running: bool,
     delta: f64,
 }
 
-// fixme
-impl Warehouse {
-    fn new(_: &eframe::CreationContext<'_>) -> Self {
-        let file = "aoc2024/src/bin/day15/day15_data.txt";
-        let mut robot: Robot = Default::default();
-        let mut locations: HashMap<Point<usize>, Obstacle> = HashMap::new();
-        let mut instructions = Vec::new();
-        let contents = fs::read_to_string(file).expect("Can't read the file");
-        let mut map = true;
-        let mut max_x = 0;
-        let mut max_y = 0;
-        for (y, line) in contents.lines().enumerate() {
-            if line.is_empty() {
-                map = false;
-                continue;
-            }
-            if map {
-                for (x, c) in line.chars().enumerate() {
-                    match c {
-                        'O' => {
-                            locations.entry(Point { x, y }).or_insert(Obstacle::Box);
-                        }
-                        '#' => {
-                            locations.entry(Point { x, y }).or_insert(Obstacle::Wall);
-                        }
-                        '@' => {
-                            robot.pos.x = x;
-                            robot.pos.y = y;
-                        }
-                        _ => (),
-                    }
-                }
-                max_x = line.len();
-                max_y = max_y.max(y);
-            } else {
-                for i in line.chars() {
-                    match i {
-                        '^' => instructions.push(Direction::North),
-                        '>' => instructions.push(Direction::East),
-                        'v' => instructions.push(Direction::South),
-                        '<' => instructions.push(Direction::West),
-                        _ => (),
-                    }
-                }
-            }
-        }
-        let instruction_queue = VecDeque::from(instructions.clone());
-        let original_robot = robot.clone();
-        let original_locations = locations.clone();
-
-        Warehouse {
-            max_x,
-            max_y,
-            original_robot,
-            robot,
-            original_locations,
-            locations,
-            instructions,
-            instruction_queue,
-            iterations: 0,
+impl WarehouseApp {
+    fn new(_: &eframe::CreationContext<'_>, file: String) -> Self {
+        let mut app = WarehouseApp {
+            file,
             delay: 50.0,
             running: true,
-            delta: 0.0,
+            show_metrics: true,
+            ..Default::default()
+        };
+        app.load();
+        app
+    }
+
+    // (Re)read the file as part one or two and start from the top. A file that
+    // won't read leaves an empty warehouse and the error in the top panel.
+    fn load(&mut self) {
+        let box_width = if self.wide { 2 } else { 1 };
+        let warehouse = match fs::read_to_string(&self.file) {
+            Ok(contents) => {
+                self.load_error = None;
+                Warehouse::parse(&contents, box_width)
+            }
+            Err(e) => {
+                self.load_error = Some(format!("{}: {}", self.file, e));
+                Warehouse::parse("", box_width)
+            }
+        };
+        // A map with its walls drawn wide is part two whatever was asked for
+        self.wide = warehouse.box_width == 2;
+        // Play starts over from the new map rather than the old one
+        self.play_start = warehouse.clone();
+        self.original = warehouse;
+        self.reset_warehouse();
+    }
+
+    fn open_file(&mut self) {
+        let start = Path::new(&self.file)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Warehouse", &["txt"])
+            .set_directory(start)
+            .pick_file()
+        {
+            self.file = path.display().to_string();
+            self.load();
         }
     }
 
     fn reset_warehouse(&mut self) {
-        self.instruction_queue.drain(0..);
-        self.instruction_queue = VecDeque::from(self.instructions.clone());
-        self.robot = self.original_robot.clone();
-        self.locations = self.original_locations.clone();
-        self.iterations = 0;
+        self.warehouse = match self.mode {
+            Mode::Replay | Mode::Edit => self.original.clone(),
+            Mode::Play => self.play_start.clone(),
+        };
+        self.history = History::default();
+        self.metrics = Metrics::new(&self.warehouse);
     }
 
-    fn move_robot(&mut self, instruction: Direction) {
-        let mut proposed_robot_move = self.robot.pos;
-        match instruction {
-            Direction::North => proposed_robot_move.y -= 1,
-            Direction::East => proposed_robot_move.x += 1,
-            Direction::South => proposed_robot_move.y += 1,
-            Direction::West => proposed_robot_move.x -= 1,
+    // Play carries on from wherever the replay had got to, editing starts from
+    // the map as loaded
+    fn set_mode(&mut self, mode: Mode) {
+        self.switch_mode(mode);
+        match mode {
+            Mode::Replay => self.reset_warehouse(),
+            Mode::Play => {
+                self.play_start = self.warehouse.clone();
+                self.history = History::default();
+                self.metrics = Metrics::new(&self.warehouse);
+            }
+            Mode::Edit => {
+                self.reset_warehouse();
+                self.editor = Editor::from_warehouse(&self.original);
+            }
         }
+    }
 
-        if self.move_obstacle(proposed_robot_move, instruction) {
-            self.robot.pos = proposed_robot_move;
-            self.robot.moved_successfully = true;
-        } else {
-            self.robot.moved_successfully = false;
+    // Just the mode and what goes with any change of it
+    fn switch_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.running = false;
+        self.play_message.clear();
+        self.edit_message.clear();
+    }
+
+    // The file's instructions, or the ones played so far
+    fn instructions(&self) -> Vec<Direction> {
+        match self.mode {
+            Mode::Replay | Mode::Edit => self.original.instructions.clone(),
+            Mode::Play => self
+                .history
+                .moves
+                .iter()
+                .map(|step| step.direction)
+                .collect(),
         }
     }
 
-    fn move_obstacle(&mut self, proposed_move: Point<usize>, instruction: Direction) -> bool {
-        match self.locations.get(&proposed_move) {
-            Some(obstacle) => {
-                match obstacle {
-                    // wall blocks movement
-                    Obstacle::Wall => false,
-                    // if box { check if box can move, move if yes}
-                    Obstacle::Box => {
-                        let mut next_move = proposed_move;
-                        match instruction {
-                            Direction::North => next_move.y -= 1,
-                            Direction::East => next_move.x += 1,
-                            Direction::South => next_move.y += 1,
-                            Direction::West => next_move.x -= 1,
-                        }
-                        if self.move_obstacle(next_move, instruction) {
-                            // insert box at next_move
-                            self.locations.entry(next_move).or_insert(Obstacle::Box);
-                            // remove the box at proposed_move
-                            self.locations.remove(&proposed_move);
-                            true
-                        } else {
-                            false
-                        }
+    // Arrow keys move, ctrl-z or backspace undoes
+    fn play_keys(&mut self, ctx: &egui::Context) {
+        let (moves, undo) = ctx.input(|i| {
+            let moves: Vec<Direction> = [
+                (egui::Key::ArrowUp, Direction::North),
+                (egui::Key::ArrowRight, Direction::East),
+                (egui::Key::ArrowDown, Direction::South),
+                (egui::Key::ArrowLeft, Direction::West),
+            ]
+            .into_iter()
+            .filter(|(key, _)| i.key_pressed(*key))
+            .map(|(_, direction)| direction)
+            .collect();
+            let undo = i.key_pressed(egui::Key::Backspace)
+                || (i.modifiers.command && i.key_pressed(egui::Key::Z));
+            (moves, undo)
+        });
+        for direction in moves {
+            self.history.push(&mut self.warehouse, direction);
+        }
+        if undo {
+            self.step_back();
+        }
+    }
+
+    // The recording so far, and ways to get it out
+    fn play_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Arrow keys move, ctrl-z or backspace undoes.");
+            if ui.button("Undo").clicked() {
+                self.step_back();
+            }
+            if ui.button("Copy moves").clicked() {
+                ui.ctx().copy_text(self.history.instructions());
+                self.play_message = "Copied".to_string();
+            }
+            if ui.button("Export…").clicked() {
+                self.export();
+            }
+            ui.label(&self.play_message);
+        });
+        egui::ScrollArea::vertical()
+            .max_height(60.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::Label::new(RichText::new(self.history.instructions()).monospace()).wrap(),
+                );
+            });
+    }
+
+    // Save the start of play and the moves since, in the puzzle's format
+    fn export(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Warehouse", &["txt"])
+            .set_file_name("day15_played.txt")
+            .save_file()
+        else {
+            return;
+        };
+        self.play_message = match fs::write(&path, self.history.export(&self.play_start)) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("{}: {}", path.display(), e),
+        };
+    }
+
+    fn moved_successfully(&self) -> bool {
+        self.history.last().is_none_or(|step| !step.blocked)
+    }
+
+    fn seek(&mut self, index: usize) {
+        let instructions = self.instructions();
+        self.history.seek(&mut self.warehouse, &instructions, index);
+    }
+
+    fn step_forward(&mut self) {
+        self.seek(self.history.at + 1);
+    }
+
+    fn step_back(&mut self) {
+        self.history.back(&mut self.warehouse);
+    }
+
+    // Tools, size, instructions, and saving once the map is good
+    fn editor_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Editor");
+        for (tool, name) in [
+            (Tool::Wall, "Wall #"),
+            (Tool::Box, "Box O"),
+            (Tool::WideBox, "Wide box []"),
+            (Tool::Robot, "Robot @"),
+            (Tool::Erase, "Erase ."),
+        ] {
+            ui.radio_value(&mut self.tool, tool, name);
+        }
+        ui.separator();
+        let (mut width, mut height) = (self.editor.width(), self.editor.height());
+        ui.horizontal(|ui| {
+            ui.label("Size:");
+            ui.add(egui::DragValue::new(&mut width).range(3..=200));
+            ui.label("×");
+            ui.add(egui::DragValue::new(&mut height).range(3..=200));
+        });
+        if (width, height) != (self.editor.width(), self.editor.height()) {
+            self.editor.resize(width, height);
+        }
+        if ui.button("Clear").clicked() {
+            self.editor = Editor {
+                instructions: self.editor.instructions.clone(),
+                ..Editor::new(width, height)
+            };
+        }
+        ui.separator();
+        ui.label("Instructions:");
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.editor.instructions)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+        ui.separator();
+        match self.editor.validate() {
+            Ok(()) => {
+                ui.colored_label(egui::Color32::from_rgb(0, 150, 0), "Map is good");
+                if ui.button("Save…").clicked() {
+                    self.save_edit();
+                }
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+            }
+        }
+        ui.label(&self.edit_message);
+    }
+
+    // Write the map out, then replay it from the file it's now in
+    fn save_edit(&mut self) {
+        let Ok(contents) = self.editor.to_file_format() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Warehouse", &["txt"])
+            .set_file_name("day15_edited.txt")
+            .save_file()
+        else {
+            return;
+        };
+        match fs::write(&path, contents) {
+            Ok(()) => {
+                self.file = path.display().to_string();
+                self.wide = false;
+                // load resets the warehouse for the mode, so only switch it here
+                self.switch_mode(Mode::Replay);
+                self.load();
+            }
+            Err(e) => self.edit_message = format!("{}: {}", path.display(), e),
+        }
+    }
+
+    // The map being edited, painted with the current tool while the mouse is down
+    fn edit_canvas(&mut self, ui: &mut egui::Ui) {
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        // Always fitted, so the whole map is there to paint on
+        let view = Camera::default().view(
+            response.rect,
+            self.editor.width(),
+            self.editor.height(),
+            Point::default(),
+        );
+        let spacing = view.spacing;
+        let cell_rect = |x: usize, y: usize| view.cell_rect(Point { x, y });
+
+        let pos = response
+            .interact_pointer_pos()
+            .and_then(|p| view.cell_at(p));
+        if let Some(pos) = pos {
+            // Once per press for the tools that move things about. A click is only
+            // reported as the button comes up, so it can't wait for the button down.
+            if matches!(self.tool, Tool::Robot | Tool::WideBox) {
+                if response.clicked() || response.drag_started() {
+                    self.editor.paint(pos, self.tool);
+                }
+            } else if response.is_pointer_button_down_on() {
+                self.editor.paint(pos, self.tool);
+            }
+        }
+
+        for (y, row) in self.editor.grid.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let cell = cell_rect(x, y);
+                painter.rect_stroke(
+                    cell,
+                    egui::CornerRadius::default(),
+                    egui::Stroke::new(0.5, egui::Color32::DARK_GRAY),
+                    egui::StrokeKind::Inside,
+                );
+                match c {
+                    '#' => {
+                        painter.rect_filled(
+                            cell,
+                            egui::CornerRadius::default(),
+                            egui::Color32::RED,
+                        );
+                    }
+                    'O' => {
+                        painter.rect_filled(
+                            cell.shrink(1.0),
+                            egui::CornerRadius::from(spacing / 5.0f32),
+                            egui::Color32::GREEN,
+                        );
+                    }
+                    '[' => {
+                        painter.rect_filled(
+                            cell.union(cell_rect(x + 1, y)).shrink(1.0),
+                            egui::CornerRadius::from(spacing / 5.0f32),
+                            egui::Color32::GREEN,
+                        );
+                    }
+                    '@' => {
+                        painter.circle_filled(cell.center(), spacing * 0.5, egui::Color32::CYAN);
                     }
+                    c if c.is_ascii_uppercase() => {
+                        painter.rect_filled(
+                            cell,
+                            egui::CornerRadius::default(),
+                            egui::Color32::DARK_GREEN,
+                        );
+                        painter.text(
+                            cell.center(),
+                            egui::Align2::CENTER_CENTER,
+                            c,
+                            egui::FontId::monospace(spacing * 0.8),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                    _ => (),
                 }
             }
-            // No obstacle, free to move
-            None => true,
         }
     }
+
+    // The warehouse through the camera. The wheel zooms, dragging pans, and
+    // hovering over a cell says what's there.
+    fn warehouse_canvas(&mut self, ui: &mut egui::Ui) {
+        let (response, painter) =
+            ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let warehouse = &self.warehouse;
+        let view = self
+            .camera
+            .view(rect, warehouse.width, warehouse.height, warehouse.robot);
+
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            if scroll != 0.0 {
+                self.camera
+                    .zoom_at(view, rect, pointer, (scroll / 200.0).exp());
+            }
+        }
+        if response.dragged() {
+            self.camera.pan(view, rect, response.drag_delta());
+        }
+
+        // Walls, a cell at a time
+        for pos in &warehouse.walls {
+            painter.rect_filled(
+                view.cell_rect(*pos),
+                egui::CornerRadius::default(),
+                egui::Color32::RED,
+            );
+        }
+
+        // Boxes, as many cells as each covers
+        let hovered = response.hover_pos().and_then(|p| view.cell_at(p));
+        let hovered_box = hovered.and_then(|cell| warehouse.box_at(cell));
+        for (pos, size) in &warehouse.boxes {
+            let bottom_right = Point {
+                x: pos.x + size.width - 1,
+                y: pos.y + size.height - 1,
+            };
+            // 2 point gap
+            let cells = view
+                .cell_rect(*pos)
+                .union(view.cell_rect(bottom_right))
+                .shrink(1.0);
+            painter.rect_filled(
+                cells,
+                egui::CornerRadius::from(view.spacing / 5.0f32),
+                egui::Color32::GREEN,
+            );
+            if hovered_box == Some(*pos) {
+                painter.rect_stroke(
+                    cells,
+                    egui::CornerRadius::from(view.spacing / 5.0f32),
+                    egui::Stroke::new(2.0, egui::Color32::WHITE),
+                    egui::StrokeKind::Inside,
+                );
+            }
+        }
+
+        // Robot
+        let robot = view.cell_rect(warehouse.robot).center();
+        if self.moved_successfully() {
+            painter.circle_filled(robot, view.spacing * 0.5, egui::Color32::CYAN);
+        } else {
+            // robot turns the air BLUE if it can't move
+            painter.circle_filled(robot, view.spacing * 0.8, egui::Color32::BLUE);
+        }
+
+        if let Some(cell) = hovered {
+            response.on_hover_ui_at_pointer(|ui| {
+                ui.label(format!("({}, {})", cell.x, cell.y));
+                if warehouse.walls.contains(&cell) {
+                    ui.label("Wall");
+                } else if let Some(pos) = hovered_box {
+                    let size = warehouse.boxes[&pos];
+                    ui.label(format!(
+                        "{}×{} box from ({}, {})",
+                        size.width, size.height, pos.x, pos.y
+                    ));
+                    ui.label(format!("GPS {}", gps(pos)));
+                } else if cell == warehouse.robot {
+                    ui.label("Robot");
+                } else {
+                    ui.label("Empty");
+                }
+            });
+        }
+    }
+
+    // The GPS sum so far as a line, move counts, and how long boxes have sat in
+    // each cell, from yellow for hardly at all to red for the longest
+    fn metrics_panel(&self, ui: &mut egui::Ui) {
+        let metrics = &self.metrics;
+        ui.heading("Metrics");
+        ui.label(format!("GPS sum: {}", metrics.gps()));
+
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(ui.available_width(), 120.0),
+            egui::Sense::hover(),
+        );
+        let rect = response.rect;
+        painter.rect_filled(rect, egui::CornerRadius::default(), egui::Color32::BLACK);
+        let (low, high) = metrics
+            .gps_history()
+            .fold((usize::MAX, 0), |(low, high), gps| {
+                (low.min(gps), high.max(gps))
+            });
+        // no more than a point per pixel across
+        let every = (metrics.steps() + 1).div_ceil(rect.width().max(1.0) as usize);
+        let line: Vec<egui::Pos2> = metrics
+            .gps_history()
+            .enumerate()
+            .step_by(every)
+            .map(|(i, gps)| {
+                let x = i as f32 / metrics.steps().max(1) as f32;
+                let y = (gps - low) as f32 / (high - low).max(1) as f32;
+                rect.lerp_inside(egui::vec2(x, 1.0 - y))
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            line,
+            egui::Stroke::new(1.5, egui::Color32::GREEN),
+        ));
+        ui.label(format!("{} to {}", low, high));
+
+        ui.separator();
+        ui.label(format!("Moved: {}", metrics.moved()));
+        ui.label(format!("Blocked: {}", metrics.blocked()));
+        ui.label(format!("Longest push: {}", metrics.longest_push()));
+
+        ui.separator();
+        ui.label("Box heat map");
+        let warehouse = &self.warehouse;
+        let size = ui.available_width();
+        let (response, painter) = ui.allocate_painter(egui::vec2(size, size), egui::Sense::hover());
+        let rect = response.rect;
+        let view = Camera::default().view(rect, warehouse.width, warehouse.height, warehouse.robot);
+        let heat = metrics.heat();
+        let hottest = heat.values().max().copied().unwrap_or(1);
+        for pos in &warehouse.walls {
+            painter.rect_filled(
+                view.cell_rect(*pos),
+                egui::CornerRadius::default(),
+                egui::Color32::DARK_GRAY,
+            );
+        }
+        for (pos, count) in &heat {
+            let t = *count as f32 / hottest as f32;
+            let colour = egui::Color32::from_rgb(255, (255.0 * (1.0 - t)) as u8, 0);
+            painter.rect_filled(view.cell_rect(*pos), egui::CornerRadius::default(), colour);
+        }
+        if let Some(cell) = response.hover_pos().and_then(|p| view.cell_at(p)) {
+            let steps = heat.get(&cell).copied().unwrap_or(0);
+            response.on_hover_text_at_pointer(format!(
+                "({}, {}) had a box for {} of {} steps",
+                cell.x,
+                cell.y,
+                steps,
+                metrics.steps() + 1
+            ));
+        }
+    }
+
+    // Slider over every instruction, step buttons, and the instructions either
+    // side of where we are with the next one picked out
+    fn timeline(&mut self, ui: &mut egui::Ui) {
+        let instructions = self.instructions();
+        let total = instructions.len();
+        ui.horizontal(|ui| {
+            if ui.button("⏮").clicked() {
+                self.running = false;
+                self.seek(0);
+            }
+            if ui.button("◀ Back").clicked() {
+                self.running = false;
+                self.step_back();
+            }
+            if ui.button("Forward ▶").clicked() {
+                self.running = false;
+                self.step_forward();
+            }
+            if ui.button("⏭").clicked() {
+                self.running = false;
+                self.seek(total);
+            }
+            ui.separator();
+            let mut index = self.history.at;
+            ui.spacing_mut().slider_width = (ui.available_width() - 80.0).max(100.0);
+            if ui.add(egui::Slider::new(&mut index, 0..=total)).changed() {
+                self.running = false;
+                self.seek(index);
+            }
+        });
+        ui.horizontal(|ui| {
+            let at = self.history.at;
+            let window = at.saturating_sub(30)..(at + 31).min(total);
+            ui.label(RichText::new(format!("{:>6}", window.start)).monospace());
+            let mut strip = egui::text::LayoutJob::default();
+            for (i, direction) in instructions[window].iter().enumerate() {
+                let i = i + at.saturating_sub(30);
+                let mut format = egui::TextFormat::simple(
+                    egui::FontId::monospace(14.0),
+                    if i < at {
+                        egui::Color32::GRAY
+                    } else {
+                        ui.visuals().strong_text_color()
+                    },
+                );
+                if i == at {
+                    format.color = egui::Color32::BLACK;
+                    format.background = egui::Color32::YELLOW;
+                }
+                strip.append(&direction.to_char().to_string(), 0.0, format);
+            }
+            ui.label(strip);
+            ui.separator();
+            match self.history.last() {
+                Some(step) => ui.label(match step.outcome() {
+                    MoveOutcome::Moved { pushed } => format!(
+                        "{} moved the robot from ({}, {}), pushing {}",
+                        step.direction.to_char(),
+                        step.robot.x,
+                        step.robot.y,
+                        pushed
+                    ),
+                    MoveOutcome::Blocked => format!(
+                        "{} blocked at ({}, {})",
+                        step.direction.to_char(),
+                        step.robot.x,
+                        step.robot.y
+                    ),
+                }),
+                None => ui.label("Start"),
+            };
+        });
+    }
 }
 
 // Cursor AI:
@@ -221,7 +686,7 @@ visual separation between sections. The button will now size itself appropriatel
 on its content rather than being forced to fill a column width.
 */
 
-impl eframe::App for Warehouse {
+impl eframe::App for WarehouseApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("Top").show(ctx, |top_ui| {
             top_ui.horizontal(|ui| {
@@ -229,13 +694,52 @@ impl eframe::App for Warehouse {
                 if ui.button("Reset Warehouse").clicked() {
                     self.reset_warehouse();
                 }
+                if ui.button("Open…").clicked() {
+                    self.open_file();
+                }
+                let was_wide = self.wide;
+                ui.selectable_value(&mut self.wide, false, "Part one");
+                ui.selectable_value(&mut self.wide, true, "Part two");
+                if self.wide != was_wide {
+                    self.load();
+                }
+
+                ui.separator();
+
+                let mut mode = self.mode;
+                ui.selectable_value(&mut mode, Mode::Replay, "Replay");
+                ui.selectable_value(&mut mode, Mode::Play, "Play");
+                ui.selectable_value(&mut mode, Mode::Edit, "Edit");
+                if mode != self.mode {
+                    self.set_mode(mode);
+                }
+
+                ui.separator();
+
+                if ui
+                    .selectable_label(self.camera.mode == CameraMode::Fit, "Fit")
+                    .clicked()
+                {
+                    self.camera.fit();
+                }
+                if ui
+                    .selectable_label(self.camera.mode == CameraMode::Follow, "Follow robot")
+                    .clicked()
+                {
+                    self.camera.mode = CameraMode::Follow;
+                }
+                ui.toggle_value(&mut self.show_metrics, "Metrics");
                 
                 ui.separator();
                 
                 // Center section with iterations and controls
                 ui.vertical_centered(|ui| {
                     ui.horizontal(|ui| {
-                        ui.label(format!("Iterations: {}", self.iterations));
+                        ui.label(format!(
+                            "Step: {}/{}",
+                            self.history.at,
+                            self.instructions().len()
+                        ));
                         
                         ui.separator();
                         
@@ -265,75 +769,44 @@ impl eframe::App for Warehouse {
                     };
                     ui.colored_label(status_color, status_text);
                 });
+
+                ui.separator();
+
+                match &self.load_error {
+                    Some(e) => ui.colored_label(egui::Color32::RED, e),
+                    None => ui.label(&self.file),
+                };
             });
         });
+        if self.mode == Mode::Edit {
+            egui::SidePanel::left("Editor")
+                .min_width(220.0)
+                .show(ctx, |ui| self.editor_panel(ui));
+        } else {
+            egui::TopBottomPanel::bottom("Timeline").show(ctx, |ui| {
+                if self.mode == Mode::Play {
+                    self.play_panel(ui);
+                    ui.separator();
+                }
+                self.timeline(ui);
+            });
+        }
+        if self.mode == Mode::Play {
+            self.play_keys(ctx);
+        }
+        if self.mode != Mode::Edit && self.show_metrics {
+            self.metrics.follow(&self.history);
+            egui::SidePanel::right("Metrics")
+                .min_width(220.0)
+                .show(ctx, |ui| self.metrics_panel(ui));
+        }
         egui::CentralPanel::default().show(ctx, |central_ui| {
+            if self.mode == Mode::Edit {
+                self.edit_canvas(central_ui);
+                return;
+            }
             egui::Frame::canvas(central_ui.style()).show(central_ui, |canvas_ui| {
-                let pos_on_canvas = |wh_pos: Point<usize>| -> (Pos2, f32) {
-                    // input warehouse coordinates
-                    // return canvas points centre position, ui canvas spacing
-                    let canvas_rect = canvas_ui.max_rect();
-                    let spacing = (canvas_rect.width() / self.max_x as f32)
-                        .min(canvas_rect.height() / self.max_y as f32);
-                    (
-                        Pos2 {
-                            x: spacing * wh_pos.x as f32 + canvas_rect.min.x + spacing / 2.0,
-                            y: spacing * wh_pos.y as f32 + canvas_rect.min.y + spacing / 2.0,
-                        },
-                        spacing,
-                    )
-                };
-
-                // Walls and boxes
-                for (pos, obstacle) in &self.locations {
-                    let (canvas_pos, spacing) = pos_on_canvas(*pos);
-                    match obstacle {
-                        Obstacle::Wall => {
-                            canvas_ui.painter().rect_filled(
-                                egui::Rect::from_center_size(
-                                    canvas_pos,
-                                    Vec2 {
-                                        x: spacing,
-                                        y: spacing,
-                                    },
-                                ),
-                                egui::CornerRadius::default(),
-                                egui::Color32::RED,
-                            );
-                        }
-                        Obstacle::Box => {
-                            canvas_ui.painter().rect_filled(
-                                egui::Rect::from_center_size(
-                                    canvas_pos,
-                                    Vec2 {
-                                        // 2 point gap
-                                        x: spacing - 2.0,
-                                        y: spacing - 2.0,
-                                    },
-                                ),
-                                egui::CornerRadius::from(spacing / 5.0f32),
-                                egui::Color32::GREEN,
-                            );
-                        }
-                    }
-                }
-
-                // Robot
-                let (robot_pos, increment) = pos_on_canvas(self.robot.pos);
-                if self.robot.moved_successfully {
-                    canvas_ui.painter().circle_filled(
-                        robot_pos,
-                        increment * 0.5,
-                        egui::Color32::CYAN,
-                    );
-                } else {
-                    // robot turns the air BLUE if it can't move
-                    canvas_ui.painter().circle_filled(
-                        robot_pos,
-                        increment * 0.8,
-                        egui::Color32::BLUE,
-                    );
-                }
+                self.warehouse_canvas(canvas_ui);
             });
         });
 
@@ -346,12 +819,55 @@ impl eframe::App for Warehouse {
         if delta - self.delta > self.delay / 1000.0 {
             self.delta = delta;
             if self.running {
-                if let Some(i) = self.instruction_queue.pop_front() {
-                    self.move_robot(i);
-                    self.iterations += 1;
+                if self.mode == Mode::Replay && self.history.at < self.original.instructions.len() {
+                    self.step_forward();
+                } else {
+                    self.running = false;
                 }
             }
         }
         ctx.request_repaint();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::day15_in_egui::{Mode, WarehouseApp};
+    use crate::warehouse::Direction;
+    use aocutils::point::Point;
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_reload_in_play() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let file = std::env::temp_dir().join(format!(
+            "day15_reload_{}_{}.txt",
+            std::process::id(),
+            nanos
+        ));
+        fs::write(&file, "#######\n#.@O..#\n#######\n\n>").unwrap();
+        let mut app = WarehouseApp {
+            file: file.display().to_string(),
+            ..Default::default()
+        };
+        app.load();
+        app.set_mode(Mode::Play);
+        app.history.push(&mut app.warehouse, Direction::East);
+
+        // The part two toggle reloads, and play carries on from the wide map
+        app.wide = true;
+        app.load();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(app.mode, Mode::Play);
+        assert_eq!(app.play_start.box_width, 2);
+        assert_eq!(app.warehouse.box_width, 2);
+        assert_eq!(app.warehouse.robot, Point { x: 4, y: 1 });
+        assert!(app.warehouse.boxes.contains_key(&Point { x: 6, y: 1 }));
+        assert_eq!(app.history.at, 0);
+        assert_eq!(app.metrics.steps(), 0);
+    }
+}
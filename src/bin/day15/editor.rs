@@ -0,0 +1,298 @@
+use crate::warehouse::{crates, file_format, Direction, Warehouse};
+use aocutils::point::Point;
+use std::collections::HashMap;
+use std::fmt;
+
+// A map being drawn in the egui app. It's kept as the characters of the puzzle file,
+// so what's saved is exactly what's on screen and Warehouse::new reads it as drawn
+// when asked for part one, or as it is whichever part if the walls are drawn wide.
+// Nothing stops a bad map being drawn, validate says what's wrong with it before
+// it's saved.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Wall,
+    Box,
+    WideBox,
+    Robot,
+    Erase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    NoRobot,
+    Robots(usize),
+    OpenWall { x: usize, y: usize },
+    // [ without its ] or the other way round
+    HalfBox { x: usize, y: usize },
+    Crate { letter: char, x: usize, y: usize },
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NoRobot => write!(f, "there's no robot"),
+            EditError::Robots(n) => write!(f, "there are {} robots, there should be one", n),
+            EditError::OpenWall { x, y } => write!(f, "gap in the outer wall at ({}, {})", x, y),
+            EditError::HalfBox { x, y } => write!(f, "half a wide box at ({}, {})", x, y),
+            EditError::Crate { letter, x, y } => {
+                write!(f, "crate {} at ({}, {}) isn't a rectangle", letter, x, y)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Editor {
+    // grid[y][x], all rows the same length
+    pub grid: Vec<Vec<char>>,
+    // as typed, anything that isn't ^>v< is left out when saved
+    pub instructions: String,
+}
+
+impl Editor {
+    // An empty room with a wall round it
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut editor = Editor::default();
+        editor.resize(width, height);
+        editor
+    }
+
+    // The warehouse as it is, and its instructions
+    pub fn from_warehouse(warehouse: &Warehouse) -> Self {
+        Editor {
+            grid: warehouse
+                .render_map()
+                .lines()
+                .map(|line| line.chars().collect())
+                .collect(),
+            instructions: warehouse
+                .instructions
+                .iter()
+                .map(|direction| direction.to_char())
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.first().map_or(0, Vec::len)
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn get(&self, pos: Point<usize>) -> Option<char> {
+        self.grid.get(pos.y)?.get(pos.x).copied()
+    }
+
+    // Keeps what fits, and walls the new edge
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.grid.resize(height, Vec::new());
+        for (y, row) in self.grid.iter_mut().enumerate() {
+            row.resize(width, '.');
+            for (x, c) in row.iter_mut().enumerate() {
+                if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+                    *c = '#';
+                }
+            }
+        }
+    }
+
+    // Whatever was in the cell goes first, both halves of a wide box. The robot
+    // moves rather than being copied.
+    pub fn paint(&mut self, pos: Point<usize>, tool: Tool) {
+        let fits = match tool {
+            Tool::WideBox => pos.x + 1 < self.width(),
+            _ => true,
+        };
+        if self.get(pos).is_none() || !fits {
+            return;
+        }
+        self.clear(pos);
+        let (x, y) = (pos.x, pos.y);
+        match tool {
+            Tool::Wall => self.grid[y][x] = '#',
+            Tool::Box => self.grid[y][x] = 'O',
+            Tool::WideBox => {
+                self.clear(Point { x: x + 1, y });
+                self.grid[y][x] = '[';
+                self.grid[y][x + 1] = ']';
+            }
+            Tool::Robot => {
+                for c in self.grid.iter_mut().flatten() {
+                    if *c == '@' {
+                        *c = '.';
+                    }
+                }
+                self.grid[y][x] = '@';
+            }
+            Tool::Erase => (),
+        }
+    }
+
+    fn clear(&mut self, pos: Point<usize>) {
+        let (x, y) = (pos.x, pos.y);
+        match self.grid[y][x] {
+            '[' if self.get(Point { x: x + 1, y }) == Some(']') => self.grid[y][x + 1] = '.',
+            ']' if x > 0 && self.grid[y][x - 1] == '[' => self.grid[y][x - 1] = '.',
+            _ => (),
+        }
+        self.grid[y][x] = '.';
+    }
+
+    pub fn validate(&self) -> Result<(), EditError> {
+        let cells = || {
+            self.grid
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, c)| (x, y, *c)))
+        };
+        match cells().filter(|(_, _, c)| *c == '@').count() {
+            0 => return Err(EditError::NoRobot),
+            1 => (),
+            n => return Err(EditError::Robots(n)),
+        }
+        let (width, height) = (self.width(), self.height());
+        if let Some((x, y, _)) = cells().find(|(x, y, c)| {
+            (*x == 0 || *y == 0 || x + 1 == width || y + 1 == height) && *c != '#'
+        }) {
+            return Err(EditError::OpenWall { x, y });
+        }
+        for (x, y, c) in cells() {
+            let whole = match c {
+                '[' => self.get(Point { x: x + 1, y }) == Some(']'),
+                ']' => x > 0 && self.grid[y][x - 1] == '[',
+                _ => true,
+            };
+            if !whole {
+                return Err(EditError::HalfBox { x, y });
+            }
+        }
+        let lettered: HashMap<Point<usize>, char> = cells()
+            .filter(|(_, _, c)| c.is_ascii_uppercase() && *c != 'O')
+            .map(|(x, y, c)| (Point { x, y }, c))
+            .collect();
+        if let Err((letter, pos)) = crates(lettered) {
+            return Err(EditError::Crate {
+                letter,
+                x: pos.x,
+                y: pos.y,
+            });
+        }
+        Ok(())
+    }
+
+    // What to save, if the map is good
+    pub fn to_file_format(&self) -> Result<String, EditError> {
+        self.validate()?;
+        let map: Vec<String> = self.grid.iter().map(|row| row.iter().collect()).collect();
+        let instructions: Vec<Direction> = self
+            .instructions
+            .chars()
+            .filter_map(Direction::from_char)
+            .collect();
+        Ok(file_format(&map.join("\n"), &instructions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::{EditError, Editor, Tool};
+    use crate::warehouse::{Direction, Warehouse};
+    use aocutils::point::Point;
+
+    #[test]
+    fn test_paint_and_save() {
+        let mut editor = Editor::new(8, 5);
+        assert_eq!(editor.validate(), Err(EditError::NoRobot));
+        editor.paint(Point { x: 1, y: 1 }, Tool::Robot);
+        editor.paint(Point { x: 2, y: 2 }, Tool::Robot);
+        editor.paint(Point { x: 3, y: 2 }, Tool::WideBox);
+        editor.paint(Point { x: 6, y: 3 }, Tool::Wall);
+        editor.instructions = ">>\n<x^".to_string();
+        let saved = editor.to_file_format().unwrap();
+        assert_eq!(
+            saved,
+            "########\n#......#\n#.@[]..#\n#.....##\n########\n\n>><^\n"
+        );
+
+        // Single walls, so read as drawn with the [] a 2x1 box
+        let mut warehouse = Warehouse::parse(&saved, 1);
+        assert_eq!(warehouse.box_width, 1);
+        assert_eq!(warehouse.boxes[&Point { x: 3, y: 2 }].width, 2);
+        assert_eq!(warehouse.robot, Point { x: 2, y: 2 });
+        warehouse.run();
+        assert!(warehouse.boxes.contains_key(&Point { x: 5, y: 2 }));
+    }
+
+    #[test]
+    fn test_paint_over_wide_box() {
+        let mut editor = Editor::new(8, 5);
+        editor.paint(Point { x: 3, y: 2 }, Tool::WideBox);
+        // Either half takes the whole box with it
+        editor.paint(Point { x: 4, y: 2 }, Tool::Box);
+        assert_eq!(editor.grid[2].iter().collect::<String>(), "#...O..#");
+        editor.paint(Point { x: 3, y: 2 }, Tool::WideBox);
+        editor.paint(Point { x: 3, y: 2 }, Tool::Erase);
+        assert_eq!(editor.grid[2].iter().collect::<String>(), "#......#");
+        // No room for the right hand half, so nothing happens
+        editor.paint(Point { x: 7, y: 2 }, Tool::WideBox);
+        assert_eq!(editor.grid[2].iter().collect::<String>(), "#......#");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut editor = Editor::new(8, 5);
+        editor.paint(Point { x: 1, y: 1 }, Tool::Robot);
+        assert_eq!(editor.validate(), Ok(()));
+        editor.grid[3][3] = '@';
+        assert_eq!(editor.validate(), Err(EditError::Robots(2)));
+        editor.paint(Point { x: 3, y: 3 }, Tool::Erase);
+
+        editor.paint(Point { x: 7, y: 2 }, Tool::Erase);
+        assert_eq!(editor.validate(), Err(EditError::OpenWall { x: 7, y: 2 }));
+        editor.paint(Point { x: 7, y: 2 }, Tool::Wall);
+
+        editor.grid[2][4] = '[';
+        assert_eq!(editor.validate(), Err(EditError::HalfBox { x: 4, y: 2 }));
+        editor.paint(Point { x: 4, y: 2 }, Tool::WideBox);
+        // Box sizes can be mixed, as the warehouse moves any size
+        editor.paint(Point { x: 2, y: 3 }, Tool::Box);
+        assert_eq!(editor.validate(), Ok(()));
+        let mixed = Warehouse::parse(&editor.to_file_format().unwrap(), 1);
+        assert_eq!(mixed.boxes[&Point { x: 4, y: 2 }].width, 2);
+        assert_eq!(mixed.boxes[&Point { x: 2, y: 3 }].width, 1);
+        editor.paint(Point { x: 2, y: 3 }, Tool::Erase);
+
+        editor.grid[3][1] = 'A';
+        editor.grid[3][2] = 'A';
+        editor.grid[2][2] = 'A';
+        assert_eq!(
+            editor.validate(),
+            Err(EditError::Crate {
+                letter: 'A',
+                x: 1,
+                y: 2
+            })
+        );
+        editor.grid[2][1] = 'A';
+        assert_eq!(editor.validate(), Ok(()));
+        assert!(editor.to_file_format().is_ok());
+    }
+
+    #[test]
+    fn test_from_warehouse() {
+        let contents = "######\n#.O@.#\n######\n\n<>";
+        let editor = Editor::from_warehouse(&Warehouse::parse(contents, 1));
+        assert_eq!(editor.to_file_format().unwrap(), format!("{}\n", contents));
+        let editor = Editor::from_warehouse(&Warehouse::parse(contents, 2));
+        let wide = Warehouse::parse(&editor.to_file_format().unwrap(), 1);
+        assert_eq!(wide.box_width, 2);
+        assert_eq!(wide.robot, Point { x: 6, y: 1 });
+        assert_eq!(wide.instructions, vec![Direction::West, Direction::East]);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::warehouse::{file_format, Direction, Move, Warehouse};
+
+// The moves made so far, as diffs, and where in them the warehouse is. Going back
+// undoes diffs, going forward redoes them until they run out, then makes new ones.
+// Making a new move part way back drops the ones after it.
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub moves: Vec<Move>,
+    // moves[..at] are applied to the warehouse
+    pub at: usize,
+    // bumped whenever the moves change, so with `at` it says where things stand
+    generation: usize,
+}
+
+impl History {
+    // The move that got the warehouse where it is
+    pub fn last(&self) -> Option<&Move> {
+        self.at.checked_sub(1).map(|i| &self.moves[i])
+    }
+
+    pub fn push(&mut self, warehouse: &mut Warehouse, direction: Direction) -> &Move {
+        self.moves.truncate(self.at);
+        self.moves.push(warehouse.apply(direction));
+        self.at += 1;
+        self.generation += 1;
+        &self.moves[self.at - 1]
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn back(&mut self, warehouse: &mut Warehouse) -> bool {
+        let Some(i) = self.at.checked_sub(1) else {
+            return false;
+        };
+        warehouse.undo(&self.moves[i]);
+        self.at = i;
+        true
+    }
+
+    // Redo a recorded move, false if there's none
+    pub fn forward(&mut self, warehouse: &mut Warehouse) -> bool {
+        let Some(step) = self.moves.get(self.at) else {
+            return false;
+        };
+        warehouse.redo(step);
+        self.at += 1;
+        true
+    }
+
+    // To move `index` of the instructions, making moves past the recorded ones
+    // from `instructions`. Stops short if they run out.
+    pub fn seek(&mut self, warehouse: &mut Warehouse, instructions: &[Direction], index: usize) {
+        while self.at > index {
+            self.back(warehouse);
+        }
+        while self.at < index {
+            if !self.forward(warehouse) {
+                let Some(&direction) = instructions.get(self.at) else {
+                    break;
+                };
+                self.push(warehouse, direction);
+            }
+        }
+    }
+
+    // The directions moved, as puzzle input
+    pub fn instructions(&self) -> String {
+        self.moves[..self.at]
+            .iter()
+            .map(|step| step.direction.to_char())
+            .collect()
+    }
+
+    // A puzzle file: the map these moves started from, then the moves
+    pub fn export(&self, start: &Warehouse) -> String {
+        let moves: Vec<Direction> = self.moves[..self.at]
+            .iter()
+            .map(|step| step.direction)
+            .collect();
+        file_format(&start.render_map(), &moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::History;
+    use crate::warehouse::Warehouse;
+
+    const MAP: &str = "##########\n#..O..O.O#\n#......O.#\n#.OO..O.O#\n#..O@..O.#\n#O#..O...#\n#O..O..O.#\n#.OO.O.OO#\n#....O...#\n##########\n\n<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^\nvvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v\n";
+
+    // The same boxes and robot, whichever way it got there
+    fn same(a: &Warehouse, b: &Warehouse) -> bool {
+        a.boxes == b.boxes && a.robot == b.robot
+    }
+
+    #[test]
+    fn test_seek() {
+        for box_width in [1, 2] {
+            let original = Warehouse::parse(MAP, box_width);
+            let instructions = original.instructions.clone();
+            let mut warehouse = original.clone();
+            let mut history = History::default();
+            history.seek(&mut warehouse, &instructions, instructions.len());
+            let mut expected = original.clone();
+            expected.run();
+            assert!(same(&warehouse, &expected));
+
+            // Back to the start, then into the middle from either side
+            history.seek(&mut warehouse, &instructions, 0);
+            assert!(same(&warehouse, &original));
+            history.seek(&mut warehouse, &instructions, 70);
+            let mut expected = original.clone();
+            for direction in &instructions[..70] {
+                expected.step(*direction);
+            }
+            assert!(same(&warehouse, &expected));
+            history.seek(&mut warehouse, &instructions, 100);
+            history.seek(&mut warehouse, &instructions, 70);
+            assert!(same(&warehouse, &expected));
+            assert_eq!(history.moves.len(), instructions.len());
+
+            // Past the end stops at the end
+            history.seek(&mut warehouse, &instructions, instructions.len() + 5);
+            assert_eq!(history.at, instructions.len());
+        }
+    }
+
+    #[test]
+    fn test_push_after_back() {
+        let mut warehouse = Warehouse::parse(MAP, 1);
+        let instructions = warehouse.instructions.clone();
+        let mut history = History::default();
+        history.seek(&mut warehouse, &instructions, 5);
+        assert_eq!(history.instructions(), "<vv>^");
+        history.back(&mut warehouse);
+        history.back(&mut warehouse);
+        history.push(&mut warehouse, instructions[4]);
+        assert_eq!(history.instructions(), "<vv^");
+        assert_eq!(history.moves.len(), 4);
+        assert!(!history.forward(&mut warehouse));
+    }
+
+    #[test]
+    fn test_export() {
+        for box_width in [1, 2] {
+            let start = Warehouse::parse(MAP, box_width);
+            let mut warehouse = start.clone();
+            let mut history = History::default();
+            history.seek(&mut warehouse, &start.instructions, 100);
+            let exported = history.export(&start);
+            assert_eq!(exported.lines().nth(start.height + 1).unwrap().len(), 70);
+
+            // Read back and run, it ends up in the same place
+            let mut replayed = Warehouse::parse(&exported, 1);
+            assert_eq!(replayed.instructions, start.instructions[..100]);
+            replayed.run();
+            assert!(same(&replayed, &warehouse));
+        }
+    }
+}
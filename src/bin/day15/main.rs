@@ -1,9 +1,19 @@
+mod camera;
 mod day15_in_egui;
 mod day15_part_one;
 mod day15_part_two;
+mod editor;
+mod history;
+mod metrics;
+mod warehouse;
 
 use day15_in_egui::egui_main;
+use std::env;
 
+// cargo run --bin day15 -- [file]
 fn main() {
-    egui_main().unwrap();
+    let file = env::args()
+        .nth(1)
+        .unwrap_or("src/bin/day15/day15_data.txt".to_string());
+    egui_main(&file).unwrap();
 }
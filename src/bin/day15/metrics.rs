@@ -0,0 +1,277 @@
+use crate::history::History;
+use crate::warehouse::{gps, Move, Warehouse};
+use aocutils::point::Point;
+use std::collections::HashMap;
+
+// Running figures for the metrics panel, kept in step with a History. Everything
+// is worked out a move at a time from the moves' diffs, so stepping forward or
+// back costs about what the move did, not the size of the warehouse.
+
+#[derive(Debug, Clone)]
+struct Step {
+    taken: Move,
+    // after the move
+    gps: usize,
+    blocked: usize,
+    longest_push: usize,
+    // cells the move cleared, with the step they'd had a box since
+    cleared: Vec<(Point<usize>, usize)>,
+    // cells the move pushed a box into
+    filled: Vec<Point<usize>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    // where the moves so far leave it
+    warehouse: Warehouse,
+    start_gps: usize,
+    steps: Vec<Step>,
+    // steps a cell had a box in, from boxes that have since moved off it
+    occupied: HashMap<Point<usize>, usize>,
+    // the step the box in a cell now arrived
+    since: HashMap<Point<usize>, usize>,
+    // the history's generation and position when last followed
+    followed: Option<(usize, usize)>,
+}
+
+impl Metrics {
+    pub fn new(start: &Warehouse) -> Self {
+        let since = start
+            .boxes
+            .iter()
+            .flat_map(|(pos, size)| size.cells(*pos))
+            .map(|cell| (cell, 0))
+            .collect();
+        Metrics {
+            warehouse: start.clone(),
+            start_gps: start.gps_sum(),
+            since,
+            ..Default::default()
+        }
+    }
+
+    // Catch up with the moves made from the start, going back first if they
+    // don't begin with the ones seen already
+    pub fn sync(&mut self, moves: &[Move]) {
+        let same = self
+            .steps
+            .iter()
+            .zip(moves)
+            .take_while(|(step, taken)| step.taken == **taken)
+            .count();
+        while self.steps.len() > same {
+            self.back();
+        }
+        for taken in &moves[same..] {
+            self.forward(taken);
+        }
+    }
+
+    // sync with a history, only if it's moved or changed since last time, so it
+    // can be called every frame
+    pub fn follow(&mut self, history: &History) {
+        let now = Some((history.generation(), history.at));
+        if self.followed != now {
+            self.sync(&history.moves[..history.at]);
+            self.followed = now;
+        }
+    }
+
+    fn forward(&mut self, taken: &Move) {
+        let at = self.steps.len() + 1;
+        let before: Vec<Point<usize>> = taken
+            .pushed
+            .iter()
+            .flat_map(|pos| self.warehouse.boxes[pos].cells(*pos))
+            .collect();
+        self.warehouse.redo(taken);
+        let after: Vec<Point<usize>> = before
+            .iter()
+            .map(|cell| taken.direction.step(*cell).unwrap())
+            .collect();
+
+        let mut cleared = Vec::new();
+        for cell in before.iter().filter(|cell| !after.contains(cell)) {
+            let since = self.since.remove(cell).unwrap();
+            *self.occupied.entry(*cell).or_default() += at - since;
+            cleared.push((*cell, since));
+        }
+        let filled: Vec<Point<usize>> = after
+            .iter()
+            .filter(|cell| !before.contains(cell))
+            .copied()
+            .collect();
+        for cell in &filled {
+            self.since.insert(*cell, at);
+        }
+
+        let moved = taken
+            .pushed
+            .iter()
+            .map(|pos| taken.direction.step(*pos).unwrap());
+        let last = self.steps.last();
+        self.steps.push(Step {
+            taken: taken.clone(),
+            gps: self.gps() + moved.map(gps).sum::<usize>()
+                - taken.pushed.iter().copied().map(gps).sum::<usize>(),
+            blocked: last.map_or(0, |step| step.blocked) + taken.blocked as usize,
+            longest_push: last
+                .map_or(0, |step| step.longest_push)
+                .max(taken.pushed.len()),
+            cleared,
+            filled,
+        });
+    }
+
+    fn back(&mut self) {
+        let step = self.steps.pop().unwrap();
+        let at = self.steps.len() + 1;
+        for cell in &step.filled {
+            self.since.remove(cell);
+        }
+        for (cell, since) in &step.cleared {
+            *self.occupied.get_mut(cell).unwrap() -= at - since;
+            self.since.insert(*cell, *since);
+        }
+        self.warehouse.undo(&step.taken);
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn gps(&self) -> usize {
+        self.steps.last().map_or(self.start_gps, |step| step.gps)
+    }
+
+    // The GPS sum at the start and after each move
+    pub fn gps_history(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(self.start_gps).chain(self.steps.iter().map(|step| step.gps))
+    }
+
+    pub fn blocked(&self) -> usize {
+        self.steps.last().map_or(0, |step| step.blocked)
+    }
+
+    pub fn moved(&self) -> usize {
+        self.steps() - self.blocked()
+    }
+
+    // The most boxes one move has pushed
+    pub fn longest_push(&self) -> usize {
+        self.steps.last().map_or(0, |step| step.longest_push)
+    }
+
+    // How many of the states so far, the start included, had a box in each cell
+    pub fn heat(&self) -> HashMap<Point<usize>, usize> {
+        let mut heat = self.occupied.clone();
+        for (cell, since) in &self.since {
+            *heat.entry(*cell).or_default() += self.steps() + 1 - since;
+        }
+        heat.retain(|_, count| *count > 0);
+        heat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::History;
+    use crate::metrics::Metrics;
+    use crate::warehouse::Warehouse;
+    use aocutils::point::Point;
+    use std::collections::HashMap;
+
+    const MAP: &str = "##########\n#..O..O.O#\n#......O.#\n#.OO..O.O#\n#..O@..O.#\n#O#..O...#\n#O..O..O.#\n#.OO.O.OO#\n#....O...#\n##########\n\n<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^\nvvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v\n";
+
+    // Against every state from the start to `steps` moves in, the long way round
+    fn check(metrics: &Metrics, start: &Warehouse, history: &History, steps: usize) {
+        let moves = &history.moves[..steps];
+        let mut warehouse = start.clone();
+        let mut gps = vec![warehouse.gps_sum()];
+        let mut heat: HashMap<Point<usize>, usize> = HashMap::new();
+        let mut count = |warehouse: &Warehouse| {
+            for (pos, size) in &warehouse.boxes {
+                for cell in size.cells(*pos) {
+                    *heat.entry(cell).or_default() += 1;
+                }
+            }
+        };
+        count(&warehouse);
+        for taken in moves {
+            warehouse.redo(taken);
+            gps.push(warehouse.gps_sum());
+            count(&warehouse);
+        }
+        assert_eq!(metrics.steps(), steps);
+        assert_eq!(metrics.gps_history().collect::<Vec<_>>(), gps);
+        assert_eq!(metrics.heat(), heat);
+        assert_eq!(
+            metrics.blocked(),
+            moves.iter().filter(|taken| taken.blocked).count()
+        );
+        assert_eq!(
+            metrics.longest_push(),
+            moves
+                .iter()
+                .map(|taken| taken.pushed.len())
+                .max()
+                .unwrap_or(0)
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        for box_width in [1, 2] {
+            let start = Warehouse::parse(MAP, box_width);
+            let mut warehouse = start.clone();
+            let mut history = History::default();
+            history.seek(
+                &mut warehouse,
+                &start.instructions,
+                start.instructions.len(),
+            );
+
+            // Forward and back by different amounts
+            let mut metrics = Metrics::new(&start);
+            for steps in [0, 1, 40, 140, 3, 90] {
+                metrics.sync(&history.moves[..steps]);
+                check(&metrics, &start, &history, steps);
+            }
+            metrics.sync(&history.moves);
+            assert_eq!(metrics.gps(), warehouse.gps_sum());
+        }
+    }
+
+    #[test]
+    fn test_counts() {
+        let start = Warehouse::parse("########\n#.@OO..#\n########\n\n>>>>><", 1);
+        let mut warehouse = start.clone();
+        let mut history = History::default();
+        history.seek(&mut warehouse, &start.instructions, 6);
+        let mut metrics = Metrics::new(&start);
+        metrics.sync(&history.moves);
+        assert_eq!(metrics.moved(), 3);
+        assert_eq!(metrics.blocked(), 3);
+        assert_eq!(metrics.longest_push(), 2);
+        // Pushed there by the second move and stayed
+        assert_eq!(metrics.heat()[&Point { x: 6, y: 1 }], 5);
+
+        // A different move after going back replaces what came after, which
+        // following sees even though it ends up as many moves in
+        let mut followed = Metrics::new(&start);
+        history.seek(&mut warehouse, &start.instructions, 2);
+        followed.follow(&history);
+        history.back(&mut warehouse);
+        history.push(&mut warehouse, start.instructions[5]);
+        followed.follow(&history);
+        assert_eq!(followed.steps(), 2);
+        assert_eq!(followed.gps(), warehouse.gps_sum());
+        history.seek(&mut warehouse, &start.instructions, 1);
+        history.push(&mut warehouse, start.instructions[5]);
+        metrics.sync(&history.moves);
+        assert_eq!(metrics.steps(), 2);
+        assert_eq!(metrics.longest_push(), 2);
+        assert_eq!(metrics.heat().get(&Point { x: 6, y: 1 }), None);
+        assert_eq!(metrics.gps(), warehouse.gps_sum());
+    }
+}
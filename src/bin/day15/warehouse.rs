@@ -0,0 +1,1142 @@
+use aocutils::point::Point;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+
+// The warehouse, shared by both parts and the egui app.
+//
+// Part one boxes are one cell wide, part two widens the map so everything is two
+// cells wide. Rather than special case the two, every box is a rectangle kept by its
+// top left cell, and walls are kept cell by cell. The narrow input is widened by
+// box_width as it's read; a map whose walls are already drawn wide is read as it
+// is. Either way O is a 1x1 box and [] a 2x1 one before widening, so a map can mix
+// the two.
+//
+// Bigger crates are drawn with a capital letter, one rectangle of the same letter
+// per crate, so neighbouring crates need different letters:
+//
+//     ##########
+//     #..AA.O..#      A is 2x2, B is 3x1, O is the usual 1x1 box,
+//     #..AA....#      all widened alike by box_width
+//     #.BBB.@..#
+//     ##########
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub fn from_char(c: char) -> Option<Direction> {
+        match c {
+            '^' => Some(Direction::North),
+            '>' => Some(Direction::East),
+            'v' => Some(Direction::South),
+            '<' => Some(Direction::West),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    // The next cell along, None off the top or left of the map
+    pub fn step(self, pos: Point<usize>) -> Option<Point<usize>> {
+        match self {
+            Direction::North => Some(Point {
+                x: pos.x,
+                y: pos.y.checked_sub(1)?,
+            }),
+            Direction::East => Some(Point {
+                x: pos.x + 1,
+                y: pos.y,
+            }),
+            Direction::South => Some(Point {
+                x: pos.x,
+                y: pos.y + 1,
+            }),
+            Direction::West => Some(Point {
+                x: pos.x.checked_sub(1)?,
+                y: pos.y,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOutcome {
+    // the robot moved, pushing this many boxes
+    Moved { pushed: usize },
+    Blocked,
+}
+
+// What a step changed, enough to undo it or do it again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Move {
+    pub direction: Direction,
+    // where the robot was, it's still there if blocked
+    pub robot: Point<usize>,
+    // top left of each box pushed, from before the move
+    pub pushed: Vec<Point<usize>>,
+    pub blocked: bool,
+}
+
+impl Move {
+    pub fn outcome(&self) -> MoveOutcome {
+        if self.blocked {
+            MoveOutcome::Blocked
+        } else {
+            MoveOutcome::Moved {
+                pushed: self.pushed.len(),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Size {
+    // Every cell of a box of this size with its top left at pos
+    pub fn cells(self, pos: Point<usize>) -> impl Iterator<Item = Point<usize>> {
+        (pos.y..pos.y + self.height)
+            .flat_map(move |y| (pos.x..pos.x + self.width).map(move |x| Point { x, y }))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Warehouse {
+    // 1 for part one, 2 for part two or a map already drawn wide
+    pub box_width: usize,
+    pub width: usize,
+    pub height: usize,
+    pub walls: HashSet<Point<usize>>,
+    // top left cell of each box, and its size
+    pub boxes: HashMap<Point<usize>, Size>,
+    // every cell a box covers, to that box's top left
+    covered: HashMap<Point<usize>, Point<usize>>,
+    pub robot: Point<usize>,
+    pub instructions: Vec<Direction>,
+}
+
+impl Warehouse {
+    pub fn new(file: &str, box_width: usize) -> Self {
+        Self::parse(
+            &fs::read_to_string(file).expect("Can't read the file"),
+            box_width,
+        )
+    }
+
+    // Map, blank line, instructions. The map is widened by box_width unless its
+    // walls are already drawn two cells to one, see drawn_wide.
+    pub fn parse(contents: &str, box_width: usize) -> Self {
+        let mut lines = contents.lines();
+        let map: Vec<&str> = lines.by_ref().take_while(|line| !line.is_empty()).collect();
+        let (scale, box_width) = if drawn_wide(&map) {
+            (1, 2)
+        } else {
+            (box_width, box_width)
+        };
+        let mut warehouse = Warehouse {
+            box_width,
+            ..Default::default()
+        };
+        let mut lettered: HashMap<Point<usize>, char> = HashMap::new();
+        for (y, line) in map.iter().enumerate() {
+            let row: Vec<char> = line.chars().collect();
+            for (x, c) in row.iter().enumerate() {
+                let pos = Point { x: x * scale, y };
+                let size = |width| Size {
+                    width: width * scale,
+                    height: 1,
+                };
+                match c {
+                    '#' => {
+                        for dx in 0..scale {
+                            warehouse.walls.insert(Point { x: pos.x + dx, y });
+                        }
+                    }
+                    'O' => warehouse.add_box(pos, size(1)),
+                    '[' if row.get(x + 1) == Some(&']') => warehouse.add_box(pos, size(2)),
+                    '@' => warehouse.robot = pos,
+                    'A'..='Z' => {
+                        lettered.insert(Point { x, y }, *c);
+                    }
+                    _ => (),
+                }
+            }
+            warehouse.width = warehouse.width.max(row.len() * scale);
+            warehouse.height = y + 1;
+        }
+        let crates = crates(lettered).unwrap_or_else(|(letter, pos)| {
+            panic!("Crate {} at {:?} isn't a rectangle", letter, pos)
+        });
+        for (pos, size) in crates {
+            warehouse.add_box(
+                Point {
+                    x: pos.x * scale,
+                    y: pos.y,
+                },
+                Size {
+                    width: size.width * scale,
+                    height: size.height,
+                },
+            );
+        }
+        warehouse.instructions = lines
+            .flat_map(|line| line.chars())
+            .filter_map(Direction::from_char)
+            .collect();
+        warehouse
+    }
+
+    pub fn add_box(&mut self, pos: Point<usize>, size: Size) {
+        for cell in size.cells(pos) {
+            self.covered.insert(cell, pos);
+        }
+        self.boxes.insert(pos, size);
+    }
+
+    pub fn remove_box(&mut self, pos: Point<usize>) -> Option<Size> {
+        let size = self.boxes.remove(&pos)?;
+        for cell in size.cells(pos) {
+            self.covered.remove(&cell);
+        }
+        Some(size)
+    }
+
+    // The box covering a cell, by its top left cell
+    pub fn box_at(&self, pos: Point<usize>) -> Option<Point<usize>> {
+        self.covered.get(&pos).copied()
+    }
+
+    // Cells a box would move into. A box on the top or left edge can't go any
+    // further, which counts the same as a wall.
+    fn pushed_into(&self, pos: Point<usize>, direction: Direction) -> Option<Vec<Point<usize>>> {
+        let size = self.boxes[&pos];
+        let leading: Vec<Point<usize>> = match direction {
+            Direction::North => Size { height: 1, ..size }.cells(pos).collect(),
+            Direction::South => Size { height: 1, ..size }
+                .cells(Point {
+                    x: pos.x,
+                    y: pos.y + size.height - 1,
+                })
+                .collect(),
+            Direction::West => Size { width: 1, ..size }.cells(pos).collect(),
+            Direction::East => Size { width: 1, ..size }
+                .cells(Point {
+                    x: pos.x + size.width - 1,
+                    y: pos.y,
+                })
+                .collect(),
+        };
+        leading
+            .into_iter()
+            .map(|cell| direction.step(cell))
+            .collect()
+    }
+
+    // Every box a move would shift, found breadth first from the cell the robot is
+    // moving into, or None if any of them would go into a wall. Only the boxes in
+    // the way get looked at, never the rest of the map.
+    pub fn plan_push(&self, direction: Direction) -> Option<Vec<Point<usize>>> {
+        let mut displaced = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut check = vec![direction.step(self.robot)?];
+        loop {
+            for cell in check {
+                if self.walls.contains(&cell) {
+                    return None;
+                }
+                if let Some(pos) = self.box_at(cell) {
+                    if seen.insert(pos) {
+                        displaced.push(pos);
+                        queue.push_back(pos);
+                    }
+                }
+            }
+            let Some(pos) = queue.pop_front() else {
+                break;
+            };
+            // two wide boxes can both push the one in front, seen keeps it to once
+            check = self.pushed_into(pos, direction)?;
+        }
+        Some(displaced)
+    }
+
+    pub fn step(&mut self, direction: Direction) -> MoveOutcome {
+        self.apply(direction).outcome()
+    }
+
+    // Plan the push, then shift every displaced box at once. A blocked move leaves
+    // everything where it was.
+    pub fn apply(&mut self, direction: Direction) -> Move {
+        let robot = self.robot;
+        let Some(pushed) = self.plan_push(direction) else {
+            return Move {
+                direction,
+                robot,
+                pushed: Vec::new(),
+                blocked: true,
+            };
+        };
+        self.shift(&pushed, direction);
+        self.robot = direction.step(robot).unwrap();
+        Move {
+            direction,
+            robot,
+            pushed,
+            blocked: false,
+        }
+    }
+
+    // Put everything back as it was before the move
+    pub fn undo(&mut self, step: &Move) {
+        if step.blocked {
+            return;
+        }
+        let moved: Vec<Point<usize>> = step
+            .pushed
+            .iter()
+            .map(|pos| step.direction.step(*pos).unwrap())
+            .collect();
+        self.shift(&moved, step.direction.opposite());
+        self.robot = step.robot;
+    }
+
+    // The move again, straight from the diff without planning
+    pub fn redo(&mut self, step: &Move) {
+        if step.blocked {
+            return;
+        }
+        self.shift(&step.pushed, step.direction);
+        self.robot = step.direction.step(step.robot).unwrap();
+    }
+
+    // Boxes by top left, all one cell along. They're all lifted before any go
+    // down, as they can move into each other's cells.
+    fn shift(&mut self, boxes: &[Point<usize>], direction: Direction) {
+        let sizes: Vec<Size> = boxes
+            .iter()
+            .map(|pos| self.remove_box(*pos).unwrap())
+            .collect();
+        for (pos, size) in boxes.iter().zip(sizes) {
+            self.add_box(direction.step(*pos).unwrap(), size);
+        }
+    }
+
+    // Every instruction from the input, in order
+    pub fn run(&mut self) {
+        for direction in self.instructions.clone() {
+            self.step(direction);
+        }
+    }
+
+    pub fn gps_sum(&self) -> usize {
+        self.boxes.keys().copied().map(gps).sum()
+    }
+
+    // The map drawn the way parse reads it, cell for cell: O for 1x1 boxes, [] for
+    // 2x1 ones, and a letter for any other crate, different from the crates
+    // touching it
+    pub fn render_map(&self) -> String {
+        let cells = self
+            .walls
+            .iter()
+            .chain(self.covered.keys())
+            .chain([&self.robot]);
+        let width = cells
+            .clone()
+            .map(|p| p.x + 1)
+            .max()
+            .unwrap()
+            .max(self.width);
+        let height = cells.map(|p| p.y + 1).max().unwrap().max(self.height);
+        let mut grid = vec![vec!['.'; width]; height];
+        for wall in &self.walls {
+            grid[wall.y][wall.x] = '#';
+        }
+
+        let mut boxes: Vec<(&Point<usize>, &Size)> = self.boxes.iter().collect();
+        boxes.sort_by_key(|(pos, _)| (pos.y, pos.x));
+        let mut lettered: HashMap<Point<usize>, char> = HashMap::new();
+        for (pos, size) in boxes {
+            match (size.width, size.height) {
+                (1, 1) => grid[pos.y][pos.x] = 'O',
+                (2, 1) => {
+                    grid[pos.y][pos.x] = '[';
+                    grid[pos.y][pos.x + 1] = ']';
+                }
+                _ => {
+                    let touching: HashSet<char> = size
+                        .cells(*pos)
+                        .flat_map(|cell| {
+                            [
+                                Direction::North,
+                                Direction::East,
+                                Direction::South,
+                                Direction::West,
+                            ]
+                            .map(|direction| direction.step(cell))
+                        })
+                        .flatten()
+                        .filter_map(|cell| lettered.get(&cell).copied())
+                        .collect();
+                    let letter = ('A'..='Z')
+                        .find(|c| *c != 'O' && !touching.contains(c))
+                        .unwrap();
+                    for cell in size.cells(*pos) {
+                        lettered.insert(cell, letter);
+                        grid[cell.y][cell.x] = letter;
+                    }
+                }
+            }
+        }
+        grid[self.robot.y][self.robot.x] = '@';
+        grid.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // The map as it is after the first `steps` instructions from here, or after
+    // all of them if there aren't that many
+    pub fn render_at(&self, steps: usize) -> String {
+        let mut warehouse = self.clone();
+        for direction in self.instructions.iter().take(steps) {
+            warehouse.step(*direction);
+        }
+        warehouse.render_map()
+    }
+}
+
+// Whether a map is already drawn wide: it has walls, and every one of them is
+// half of a pair starting on an even column, as widening leaves them. Going by
+// the walls rather than the boxes means a wide map with no boxes, or with only
+// 1x1 ones, isn't widened a second time.
+fn drawn_wide(map: &[&str]) -> bool {
+    let mut walls = false;
+    for line in map {
+        let row: Vec<char> = line.chars().collect();
+        for pair in row.chunks(2) {
+            let count = pair.iter().filter(|c| **c == '#').count();
+            if count == 1 {
+                return false;
+            }
+            walls |= count == 2;
+        }
+    }
+    walls
+}
+
+// Top left and size
+pub type Crate = (Point<usize>, Size);
+
+// A box's GPS coordinate, from its top left cell
+pub fn gps(pos: Point<usize>) -> usize {
+    pos.y * 100 + pos.x
+}
+
+// A puzzle file from a map and moves, the moves in lines of 70 like the examples
+pub fn file_format(map: &str, instructions: &[Direction]) -> String {
+    let lines: Vec<String> = instructions
+        .chunks(70)
+        .map(|line| line.iter().map(|direction| direction.to_char()).collect())
+        .collect();
+    format!("{}\n\n{}\n", map, lines.join("\n"))
+}
+
+// Lettered cells grouped into crates, each a run of touching cells with the same
+// letter that has to make a rectangle. Err has the letter and top left of the first
+// that doesn't.
+pub fn crates(
+    mut lettered: HashMap<Point<usize>, char>,
+) -> Result<Vec<Crate>, (char, Point<usize>)> {
+    let mut res = Vec::new();
+    while let Some((&start, &letter)) = lettered.iter().next() {
+        lettered.remove(&start);
+        let mut cells = vec![start];
+        let mut queue = vec![start];
+        while let Some(pos) = queue.pop() {
+            for direction in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                if let Some(next) = direction.step(pos) {
+                    if lettered.get(&next) == Some(&letter) {
+                        lettered.remove(&next);
+                        cells.push(next);
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+        let top_left = Point {
+            x: cells.iter().map(|p| p.x).min().unwrap(),
+            y: cells.iter().map(|p| p.y).min().unwrap(),
+        };
+        let size = Size {
+            width: cells.iter().map(|p| p.x).max().unwrap() - top_left.x + 1,
+            height: cells.iter().map(|p| p.y).max().unwrap() - top_left.y + 1,
+        };
+        if cells.len() != size.width * size.height {
+            return Err((letter, top_left));
+        }
+        res.push((top_left, size));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warehouse::{Direction, MoveOutcome, Size, Warehouse};
+    use aocutils::point::Point;
+    use std::fmt;
+
+    #[test]
+    fn test_narrow() {
+        let mut warehouse = Warehouse::new("src/bin/day15/day15_basic.txt", 1);
+        warehouse.run();
+        assert_eq!(warehouse.gps_sum(), 2028);
+        let mut warehouse = Warehouse::new("src/bin/day15/day15_test.txt", 1);
+        warehouse.run();
+        assert_eq!(warehouse.gps_sum(), 10092);
+    }
+
+    #[test]
+    fn test_wide() {
+        let mut warehouse = Warehouse::new("src/bin/day15/day15_test.txt", 2);
+        assert_eq!(warehouse.width, 20);
+        warehouse.run();
+        assert_eq!(warehouse.gps_sum(), 9021);
+    }
+
+    #[test]
+    fn test_parse_wide_format() {
+        let warehouse = Warehouse::parse("########\n##[]..##\n##@...##\n########\n\n^\n", 1);
+        assert_eq!(warehouse.box_width, 2);
+        assert_eq!(warehouse.boxes.len(), 1);
+        assert!(warehouse.boxes.contains_key(&Point { x: 2, y: 1 }));
+        assert_eq!(warehouse.robot, Point { x: 2, y: 2 });
+        assert_eq!(warehouse.instructions, vec![Direction::North]);
+        // Wide boxes are found from either half
+        assert_eq!(
+            warehouse.box_at(Point { x: 3, y: 1 }),
+            Some(Point { x: 2, y: 1 })
+        );
+
+        // Told apart by the walls, so an empty wide map isn't widened again
+        let empty = "########\n##..@.##\n########";
+        let warehouse = Warehouse::parse(empty, 1);
+        assert_eq!(warehouse.box_width, 2);
+        assert_eq!(warehouse.width, 8);
+        assert_eq!(warehouse.render_map(), empty);
+        // And O and [] can be mixed either way
+        let mixed = Warehouse::parse("#######\n#O.[]@#\n#######", 2);
+        assert_eq!(mixed.box_width, 2);
+        assert_eq!(mixed.boxes[&Point { x: 2, y: 1 }].width, 2);
+        assert_eq!(mixed.boxes[&Point { x: 6, y: 1 }].width, 4);
+        let wide = Warehouse::parse("##########\n##O.[]@.##\n##########", 1);
+        assert_eq!(wide.boxes[&Point { x: 2, y: 1 }].width, 1);
+        assert_eq!(wide.boxes[&Point { x: 4, y: 1 }].width, 2);
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let unix = Warehouse::parse("#####\n#@O.#\n#####\n\n>>\n<\n", 1);
+        let windows = Warehouse::parse("#####\r\n#@O.#\r\n#####\r\n\r\n>>\r\n<\r\n", 1);
+        assert_eq!(windows.width, 5);
+        assert_eq!(windows.height, 3);
+        assert_eq!(windows.boxes, unix.boxes);
+        assert_eq!(windows.instructions, unix.instructions);
+        assert_eq!(windows.instructions.len(), 3);
+    }
+
+    #[test]
+    fn test_step_outcomes() {
+        // Two wide boxes stacked off centre, pushed up into the open then the wall
+        let mut warehouse = Warehouse::parse(
+            "##########\n##......##\n##......##\n##..[]..##\n##...[].##\n##...@..##\n##########",
+            1,
+        );
+        assert_eq!(
+            warehouse.step(Direction::North),
+            MoveOutcome::Moved { pushed: 2 }
+        );
+        assert_eq!(
+            warehouse.step(Direction::North),
+            MoveOutcome::Moved { pushed: 2 }
+        );
+        assert_eq!(warehouse.step(Direction::North), MoveOutcome::Blocked);
+        assert_eq!(warehouse.robot, Point { x: 5, y: 3 });
+        assert_eq!(
+            warehouse.step(Direction::West),
+            MoveOutcome::Moved { pushed: 0 }
+        );
+        assert_eq!(
+            warehouse.step(Direction::West),
+            MoveOutcome::Moved { pushed: 0 }
+        );
+        assert_eq!(
+            warehouse.step(Direction::West),
+            MoveOutcome::Moved { pushed: 0 }
+        );
+        assert_eq!(warehouse.step(Direction::West), MoveOutcome::Blocked);
+    }
+
+    #[test]
+    fn test_plan_push() {
+        // Two boxes both pushing the one above them, which only moves once
+        let mut warehouse = Warehouse::parse(
+            "##########\n##......##\n##..[]..##\n##.[][].##\n##..[]..##\n##..@...##\n##########",
+            1,
+        );
+        let mut displaced = warehouse.plan_push(Direction::North).unwrap();
+        displaced.sort_by_key(|p| (p.y, p.x));
+        assert_eq!(
+            displaced,
+            vec![
+                Point { x: 4, y: 2 },
+                Point { x: 3, y: 3 },
+                Point { x: 5, y: 3 },
+                Point { x: 4, y: 4 },
+            ]
+        );
+        assert_eq!(
+            warehouse.step(Direction::North),
+            MoveOutcome::Moved { pushed: 4 }
+        );
+        // Now the top box is against the wall and nothing moves
+        let before = warehouse.boxes.clone();
+        assert_eq!(warehouse.plan_push(Direction::North), None);
+        assert_eq!(warehouse.step(Direction::North), MoveOutcome::Blocked);
+        assert_eq!(warehouse.boxes, before);
+        assert_eq!(warehouse.robot, Point { x: 4, y: 4 });
+    }
+
+    const CRATES: &str =
+        "########\n#......#\n#..O...#\n#..AA..#\n#..AA..#\n#.BBB..#\n#..@...#\n########\n\n^^";
+
+    #[test]
+    fn test_parse_crates() {
+        let warehouse = Warehouse::parse(CRATES, 1);
+        assert_eq!(warehouse.boxes.len(), 3);
+        assert_eq!(
+            warehouse.boxes[&Point { x: 3, y: 3 }],
+            Size {
+                width: 2,
+                height: 2
+            }
+        );
+        assert_eq!(
+            warehouse.boxes[&Point { x: 2, y: 5 }],
+            Size {
+                width: 3,
+                height: 1
+            }
+        );
+        // Any cell of a crate finds its top left
+        assert_eq!(
+            warehouse.box_at(Point { x: 4, y: 4 }),
+            Some(Point { x: 3, y: 3 })
+        );
+        // Widened, crates keep their height and double their width
+        let wide = Warehouse::parse(CRATES, 2);
+        assert_eq!(
+            wide.boxes[&Point { x: 6, y: 3 }],
+            Size {
+                width: 4,
+                height: 2
+            }
+        );
+        assert_eq!(
+            wide.boxes[&Point { x: 6, y: 2 }],
+            Size {
+                width: 2,
+                height: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_push_crates() {
+        let mut warehouse = Warehouse::parse(CRATES, 1);
+        assert_eq!(warehouse.gps_sum(), 203 + 303 + 502);
+        // B pushes A by one of its cells, A pushes O
+        assert_eq!(
+            warehouse.step(Direction::North),
+            MoveOutcome::Moved { pushed: 3 }
+        );
+        assert_eq!(warehouse.gps_sum(), 103 + 203 + 402);
+        assert_eq!(warehouse.step(Direction::North), MoveOutcome::Blocked);
+        // Sideways, B goes on its own under A
+        assert_eq!(
+            warehouse.step(Direction::West),
+            MoveOutcome::Moved { pushed: 0 }
+        );
+        assert_eq!(warehouse.robot, Point { x: 2, y: 5 });
+        let mut warehouse = Warehouse::parse("######\n#.AA@#\n#.AA.#\n######", 1);
+        assert_eq!(
+            warehouse.step(Direction::West),
+            MoveOutcome::Moved { pushed: 1 }
+        );
+        assert_eq!(warehouse.step(Direction::West), MoveOutcome::Blocked);
+        assert!(warehouse.boxes.contains_key(&Point { x: 1, y: 1 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't a rectangle")]
+    fn test_crate_not_rectangle() {
+        Warehouse::parse("#####\n#AA.#\n#A@.#\n#####", 1);
+    }
+
+    #[test]
+    fn test_render_map() {
+        let narrow = "#######\n#..O..#\n#.@O..#\n#######";
+        assert_eq!(Warehouse::parse(narrow, 1).render_map(), narrow);
+        // Widened as part two draws it
+        assert_eq!(
+            Warehouse::parse(narrow, 2).render_map(),
+            "##############\n##....[]....##\n##..@.[]....##\n##############"
+        );
+        // Crates get letters back, the two touching ones different
+        let crates = "########\n#.AAB..#\n#.AAB..#\n#..@O..#\n########";
+        let warehouse = Warehouse::parse(crates, 1);
+        assert_eq!(warehouse.render_map(), crates);
+        let wide = Warehouse::parse(crates, 2);
+        let again = Warehouse::parse(&wide.render_map(), 1);
+        assert_eq!(again.boxes, wide.boxes);
+        assert_eq!(again.walls, wide.walls);
+        assert_eq!(again.robot, wide.robot);
+    }
+
+    // The puzzle's walk through the small examples, a state after each move
+    const NARROW_STEPS: &str = "Initial state:
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move <:
+########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move ^:
+########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move ^:
+########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#..@OO.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move >:
+########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+Move v:
+########
+#....OO#
+##..@..#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##..@..#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.@...#
+#...O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##.....#
+#..@O..#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move >:
+########
+#....OO#
+##.....#
+#...@O.#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move >:
+########
+#....OO#
+##.....#
+#....@O#
+#.#.O..#
+#...O..#
+#...O..#
+########
+
+Move v:
+########
+#....OO#
+##.....#
+#.....O#
+#.#.O@.#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.....#
+#.....O#
+#.#O@..#
+#...O..#
+#...O..#
+########
+
+Move <:
+########
+#....OO#
+##.....#
+#.....O#
+#.#O@..#
+#...O..#
+#...O..#
+########
+";
+
+    const WIDE_STEPS: &str = "Initial state:
+##############
+##......##..##
+##..........##
+##....[][]@.##
+##....[]....##
+##..........##
+##############
+
+Move <:
+##############
+##......##..##
+##..........##
+##...[][]@..##
+##....[]....##
+##..........##
+##############
+
+Move v:
+##############
+##......##..##
+##..........##
+##...[][]...##
+##....[].@..##
+##..........##
+##############
+
+Move v:
+##############
+##......##..##
+##..........##
+##...[][]...##
+##....[]....##
+##.......@..##
+##############
+
+Move <:
+##############
+##......##..##
+##..........##
+##...[][]...##
+##....[]....##
+##......@...##
+##############
+
+Move <:
+##############
+##......##..##
+##..........##
+##...[][]...##
+##....[]....##
+##.....@....##
+##############
+
+Move ^:
+##############
+##......##..##
+##...[][]...##
+##....[]....##
+##.....@....##
+##..........##
+##############
+
+Move ^:
+##############
+##......##..##
+##...[][]...##
+##....[]....##
+##.....@....##
+##..........##
+##############
+
+Move <:
+##############
+##......##..##
+##...[][]...##
+##....[]....##
+##....@.....##
+##..........##
+##############
+
+Move <:
+##############
+##......##..##
+##...[][]...##
+##....[]....##
+##...@......##
+##..........##
+##############
+
+Move ^:
+##############
+##......##..##
+##...[][]...##
+##...@[]....##
+##..........##
+##..........##
+##############
+
+Move ^:
+##############
+##...[].##..##
+##...@.[]...##
+##....[]....##
+##..........##
+##..........##
+##############
+";
+
+    // The first cell that isn't what a transcript says, and after how many moves.
+    // A cell off the edge of one map or the other is a space.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Divergence {
+        step: usize,
+        cell: Point<usize>,
+        expected: char,
+        actual: char,
+    }
+
+    impl fmt::Display for Divergence {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "after {} moves ({}, {}) is {:?}, expected {:?}",
+                self.step, self.cell.x, self.cell.y, self.actual, self.expected
+            )
+        }
+    }
+
+    // A transcript's first map
+    fn initial(transcript: &str) -> &str {
+        let (_, map) = transcript
+            .split("\n\n")
+            .next()
+            .unwrap()
+            .split_once('\n')
+            .unwrap();
+        map
+    }
+
+    // Run the moves a transcript names from its initial state, comparing every
+    // state it gives with render_at
+    fn diverges(transcript: &str) -> Result<(), Divergence> {
+        let states: Vec<(Option<Direction>, &str)> = transcript
+            .split("\n\n")
+            .map(|block| {
+                let (title, map) = block.split_once('\n').unwrap();
+                let direction = title
+                    .strip_prefix("Move ")
+                    .and_then(|title| title.chars().next())
+                    .and_then(Direction::from_char);
+                (direction, map.trim_end())
+            })
+            .collect();
+        let mut warehouse = Warehouse::parse(states[0].1, 1);
+        warehouse.instructions = states[1..]
+            .iter()
+            .map(|(direction, _)| direction.unwrap())
+            .collect();
+
+        for (step, (_, expected)) in states.iter().enumerate() {
+            let actual = warehouse.render_at(step);
+            let (expected, actual): (Vec<&str>, Vec<&str>) =
+                (expected.lines().collect(), actual.lines().collect());
+            let at = |lines: &[&str], x: usize, y: usize| {
+                lines
+                    .get(y)
+                    .and_then(|line| line.chars().nth(x))
+                    .unwrap_or(' ')
+            };
+            let height = expected.len().max(actual.len());
+            for y in 0..height {
+                let width = [&expected, &actual]
+                    .iter()
+                    .filter_map(|lines| lines.get(y))
+                    .map(|line| line.chars().count())
+                    .max()
+                    .unwrap();
+                for x in 0..width {
+                    let (expected, actual) = (at(&expected, x, y), at(&actual, x, y));
+                    if expected != actual {
+                        return Err(Divergence {
+                            step,
+                            cell: Point { x, y },
+                            expected,
+                            actual,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_example_steps() {
+        for transcript in [NARROW_STEPS, WIDE_STEPS] {
+            if let Err(divergence) = diverges(transcript) {
+                panic!("{}", divergence);
+            }
+        }
+        // Part two's initial state is the narrow map widened
+        let narrow = "#######\n#...#.#\n#.....#\n#..OO@#\n#..O..#\n#.....#\n#######";
+        assert_eq!(
+            Warehouse::parse(narrow, 2).render_map(),
+            initial(WIDE_STEPS)
+        );
+    }
+
+    #[test]
+    fn test_render_at() {
+        let mut warehouse = Warehouse::parse(initial(NARROW_STEPS), 1);
+        assert_eq!(warehouse.render_at(0), warehouse.render_map());
+        warehouse.instructions = vec![Direction::North, Direction::East];
+        assert_eq!(warehouse.render_at(2).lines().nth(1), Some("#..@OO.#"));
+        // Past the end stops at the end
+        assert_eq!(warehouse.render_at(5), warehouse.render_at(2));
+    }
+
+    #[test]
+    fn test_divergence() {
+        // One cell wrong, four moves in
+        let wrong = NARROW_STEPS.replacen("#..@OO.#", "#..@O.O#", 1);
+        assert_eq!(
+            diverges(&wrong),
+            Err(Divergence {
+                step: 4,
+                cell: Point { x: 5, y: 1 },
+                expected: '.',
+                actual: 'O',
+            })
+        );
+        // A row short after the first move
+        let short = NARROW_STEPS.replacen("#......#\n########\n\nMove ^", "########\n\nMove ^", 1);
+        assert_eq!(
+            diverges(&short),
+            Err(Divergence {
+                step: 1,
+                cell: Point { x: 1, y: 6 },
+                expected: '#',
+                actual: '.',
+            })
+        );
+    }
+}
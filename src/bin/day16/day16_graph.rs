@@ -1,11 +1,13 @@
+use crate::lpa_star::Lpa;
 use aocutils::point::Point;
 use num::{abs, ToPrimitive};
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::ops::Range;
 use std::{fs, i32};
 
-// Undirected, weight 1 graph from an array of 'walls'. 'S' & 'E' mark start, end
+// Undirected, weight 1 graph from an array of 'walls'. 'S' & 'E' mark start, end,
+// digits '1' to '9' mark waypoints a route can be asked to visit
 
 #[derive(Debug, Clone)]
 pub(crate) struct Node {
@@ -15,6 +17,95 @@ pub(crate) struct Node {
     pub(crate) seen: bool,
 }
 
+// Which way the Reindeer is facing. Moving straight on costs 1, turning 90 degrees
+// and moving costs 1001, and it never reverses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum Heading {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    pub(crate) const ALL: [Heading; 4] =
+        [Heading::North, Heading::East, Heading::South, Heading::West];
+
+    // Heading taken by a single step between two adjacent points
+    pub(crate) fn between(from: Point<i32>, to: Point<i32>) -> Heading {
+        match (to.x - from.x, to.y - from.y) {
+            (0, -1) => Heading::North,
+            (1, 0) => Heading::East,
+            (0, 1) => Heading::South,
+            (-1, 0) => Heading::West,
+            _ => panic!("{:?} and {:?} are not adjacent", from, to),
+        }
+    }
+
+    pub(crate) fn reverse(&self) -> Heading {
+        match self {
+            Heading::North => Heading::South,
+            Heading::East => Heading::West,
+            Heading::South => Heading::North,
+            Heading::West => Heading::East,
+        }
+    }
+
+    pub(crate) fn right(&self) -> Heading {
+        match self {
+            Heading::North => Heading::East,
+            Heading::East => Heading::South,
+            Heading::South => Heading::West,
+            Heading::West => Heading::North,
+        }
+    }
+
+    pub(crate) fn left(&self) -> Heading {
+        self.right().reverse()
+    }
+
+    // One step forward from pos
+    pub(crate) fn step(&self, pos: Point<i32>) -> Point<i32> {
+        match self {
+            Heading::North => Point {
+                x: pos.x,
+                y: pos.y - 1,
+            },
+            Heading::East => Point {
+                x: pos.x + 1,
+                y: pos.y,
+            },
+            Heading::South => Point {
+                x: pos.x,
+                y: pos.y + 1,
+            },
+            Heading::West => Point {
+                x: pos.x - 1,
+                y: pos.y,
+            },
+        }
+    }
+
+    // Cost of a step in `next` direction when currently facing `self`, None if
+    // it means turning around
+    pub(crate) fn step_cost(&self, next: Heading) -> Option<i32> {
+        if next == *self {
+            Some(1)
+        } else if next == self.reverse() {
+            None
+        } else {
+            Some(1001)
+        }
+    }
+
+    // As step_cost, but turning right round is two turns and a step. Routes through
+    // waypoints need it to get back out of a waypoint at the end of a dead end, and
+    // Yen's paths to set off west from a start facing east.
+    pub(crate) fn reversing_cost(&self, next: Heading) -> i32 {
+        self.step_cost(next).unwrap_or(2001)
+    }
+}
+
 #[derive(Debug)]
 pub struct Graph {
     pub adjacency_list: HashMap<Point<i32>, Vec<Point<i32>>>,
@@ -25,7 +116,11 @@ pub struct Graph {
     pub(crate) yrange: Range<i32>,
     pub(crate) start: Point<i32>,
     pub(crate) end: Point<i32>,
+    // in digit order
+    pub(crate) waypoints: Vec<Point<i32>>,
     pub(crate) plot_sequence: usize,
+    // search state kept between wall changes for incremental replanning
+    pub(crate) lpa: Option<Lpa>,
 }
 
 impl Graph {
@@ -37,6 +132,7 @@ impl Graph {
         let mut adjacency_list = HashMap::new();
         let mut node_list = HashMap::new();
         let mut walls = HashSet::new();
+        let mut waypoints = BTreeMap::new();
         let mut maze: Vec<Vec<char>> = Vec::new();
 
         // process the grid into adjacency_list & node_list
@@ -59,14 +155,17 @@ impl Graph {
                 let ix = x as i32;
                 let pos = Point { x: ix, y: iy };
                 match c {
-                    '.' | 'S' | 'E' => {
-                        // record start and end coords
+                    '.' | 'S' | 'E' | '1'..='9' => {
+                        // record start, end and waypoint coords
                         if *c == 'S' {
                             start = pos;
                         }
                         if *c == 'E' {
                             end = pos;
                         }
+                        if c.is_ascii_digit() {
+                            waypoints.insert(*c, pos);
+                        }
                         // process edges from cardinal points where not a wall
                         let mut edges: Vec<Point<i32>> = Vec::new();
                         for cardinal in pos.cardinal_points() {
@@ -74,7 +173,7 @@ impl Graph {
                                 let n = maze[cardinal.y.to_usize().unwrap()]
                                     [cardinal.x.to_usize().unwrap()];
                                 match n {
-                                    '.' | 'S' | 'E' => edges.push(cardinal),
+                                    '.' | 'S' | 'E' | '1'..='9' => edges.push(cardinal),
                                     _ => (),
                                 }
                             }
@@ -105,8 +204,61 @@ impl Graph {
             yrange,
             start,
             end,
+            waypoints: waypoints.into_values().collect(),
             plot_sequence: 0,
+            lpa: None,
+        }
+    }
+
+    // Turn an open cell into a wall, dropping its node and the edges to it.
+    // Start and end can't be walled. Returns true if the maze changed. Any LPA*
+    // state is dropped with it, lpa_add_wall keeps it up to date instead.
+    pub(crate) fn add_wall(&mut self, pos: Point<i32>) -> bool {
+        if pos == self.start || pos == self.end {
+            return false;
+        }
+        let Some(edges) = self.adjacency_list.remove(&pos) else {
+            return false;
+        };
+        for neighbour in edges {
+            if let Some(n) = self.adjacency_list.get_mut(&neighbour) {
+                n.retain(|e| *e != pos);
+            }
+        }
+        self.node_list.remove(&pos);
+        self.walls.insert(pos);
+        self.lpa = None;
+        true
+    }
+
+    // Open up a wall inside the maze, joining it to any open cardinal neighbours.
+    // Returns true if the maze changed. Drops any LPA* state, as add_wall does.
+    pub(crate) fn remove_wall(&mut self, pos: Point<i32>) -> bool {
+        if !self.xrange.contains(&pos.x) || !self.yrange.contains(&pos.y) {
+            return false;
+        }
+        if !self.walls.remove(&pos) {
+            return false;
+        }
+        let mut edges: Vec<Point<i32>> = Vec::new();
+        for cardinal in pos.cardinal_points() {
+            if let Some(n) = self.adjacency_list.get_mut(&cardinal) {
+                n.push(pos);
+                edges.push(cardinal);
+            }
         }
+        self.adjacency_list.insert(pos, edges);
+        self.node_list.insert(
+            pos,
+            Node {
+                g_cost: i32::MAX,
+                f_est_cost: 0,
+                came_from: None,
+                seen: false,
+            },
+        );
+        self.lpa = None;
+        true
     }
 
     pub fn show_path(&mut self) -> Vec<Point<i32>> {
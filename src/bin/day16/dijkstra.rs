@@ -1,8 +1,14 @@
-use crate::day16_graph::{Graph, Node};
+use crate::day16_graph::{Graph, Heading, Node};
 use aocutils::point::Point;
 use num::abs;
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
+
+// Position plus the way the Reindeer is facing there
+pub(crate) type HeadingState = (Point<i32>, Heading);
+
+// Cost to reach a state, and the state it was reached from
+pub(crate) type HeadingTree = HashMap<HeadingState, (i32, Option<HeadingState>)>;
 
 impl Graph {
     // Dijkstra's shortest path algorithm. From BinaryHeap docs, modified to the puzzle.
@@ -168,4 +174,44 @@ impl Graph {
         // Goal not reachable
         None
     }
+
+    // Dijkstra over (position, heading) from a single state, run to exhaustion so
+    // every reachable state gets its cost and where it was reached from. Turns are
+    // costed exactly, rather than from the previous position as above.
+    pub(crate) fn heading_dijkstra(&self, from: HeadingState) -> HeadingTree {
+        let mut tree: HeadingTree = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        tree.insert(from, (0, None));
+        heap.push(Reverse((0, from)));
+
+        while let Some(Reverse((cost, (position, heading)))) = heap.pop() {
+            if cost > tree[&(position, heading)].0 {
+                continue;
+            }
+            if let Some(edges) = self.adjacency_list.get(&position) {
+                for node in edges {
+                    let next_heading = Heading::between(position, *node);
+                    let next_cost = cost + heading.reversing_cost(next_heading);
+                    let next = (*node, next_heading);
+                    if tree.get(&next).is_none_or(|(c, _)| next_cost < *c) {
+                        tree.insert(next, (next_cost, Some((position, heading))));
+                        heap.push(Reverse((next_cost, next)));
+                    }
+                }
+            }
+        }
+        tree
+    }
+
+    // Walk a heading_dijkstra tree back from `to`, giving positions in start to end order
+    pub(crate) fn heading_path(tree: &HeadingTree, to: HeadingState) -> Vec<Point<i32>> {
+        let mut res = vec![to.0];
+        let mut next = tree[&to].1;
+        while let Some(state) = next {
+            res.push(state.0);
+            next = tree[&state].1;
+        }
+        res.reverse();
+        res
+    }
 }
@@ -0,0 +1,224 @@
+use crate::day16_graph::{Graph, Heading};
+use aocutils::point::Point;
+use num::abs;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// Lifelong Planning A* (Koenig & Likhachev). Same answer as A*, but keeps g/rhs
+// between runs so that when a wall is added or removed only the part of the
+// shortest path tree that depended on it gets repaired.
+//
+// Searches over (position, heading) so turns are costed exactly. All the end cell's
+// headings lead to a single Goal state at zero cost.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum State {
+    Cell(Point<i32>, Heading),
+    Goal,
+}
+
+// Priority key, compared lexicographically: (f, g)
+type Key = (i32, i32);
+
+#[derive(Debug, Default)]
+pub(crate) struct Lpa {
+    g: HashMap<State, i32>,
+    rhs: HashMap<State, i32>,
+    // Min-heap with lazy deletion. Entries are checked against the current key
+    // when popped and discarded if stale.
+    heap: BinaryHeap<Reverse<(Key, State)>>,
+    // Number of states expanded by the last compute, to compare against a rerun
+    pub(crate) expanded: usize,
+}
+
+impl Lpa {
+    fn g(&self, s: &State) -> i32 {
+        *self.g.get(s).unwrap_or(&i32::MAX)
+    }
+
+    fn rhs(&self, s: &State) -> i32 {
+        *self.rhs.get(s).unwrap_or(&i32::MAX)
+    }
+}
+
+impl Graph {
+    fn lpa_start(&self) -> State {
+        // Reindeer starts facing east
+        State::Cell(self.start, Heading::East)
+    }
+
+    fn lpa_heuristic(&self, s: &State) -> i32 {
+        match s {
+            State::Cell(p, _) => abs(self.end.x - p.x) + abs(self.end.y - p.y),
+            State::Goal => 0,
+        }
+    }
+
+    fn lpa_key(&self, lpa: &Lpa, s: &State) -> Key {
+        let m = lpa.g(s).min(lpa.rhs(s));
+        (m.saturating_add(self.lpa_heuristic(s)), m)
+    }
+
+    // States reachable from s, with the cost of getting there
+    fn lpa_successors(&self, s: &State) -> Vec<(State, i32)> {
+        let mut res = Vec::new();
+        if let State::Cell(p, h) = s {
+            if let Some(edges) = self.adjacency_list.get(p) {
+                for next in edges {
+                    let d = Heading::between(*p, *next);
+                    if let Some(cost) = h.step_cost(d) {
+                        res.push((State::Cell(*next, d), cost));
+                    }
+                }
+            }
+            if *p == self.end {
+                res.push((State::Goal, 0));
+            }
+        }
+        res
+    }
+
+    // States that can reach s, with the cost of the step
+    fn lpa_predecessors(&self, s: &State) -> Vec<(State, i32)> {
+        let mut res = Vec::new();
+        match s {
+            State::Cell(p, d) => {
+                if let Some(edges) = self.adjacency_list.get(p) {
+                    for prev in edges {
+                        if Heading::between(*prev, *p) != *d {
+                            continue;
+                        }
+                        for h in Heading::ALL {
+                            if let Some(cost) = h.step_cost(*d) {
+                                res.push((State::Cell(*prev, h), cost));
+                            }
+                        }
+                    }
+                }
+            }
+            State::Goal => {
+                if self.adjacency_list.contains_key(&self.end) {
+                    for h in Heading::ALL {
+                        res.push((State::Cell(self.end, h), 0));
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    fn lpa_update_state(&self, lpa: &mut Lpa, s: State) {
+        if s != self.lpa_start() {
+            let best = self
+                .lpa_predecessors(&s)
+                .iter()
+                .map(|(p, cost)| lpa.g(p).saturating_add(*cost))
+                .min()
+                .unwrap_or(i32::MAX);
+            lpa.rhs.insert(s, best);
+        }
+        // Locally inconsistent states go (back) on the queue, any older entry
+        // for s is now stale
+        if lpa.g(&s) != lpa.rhs(&s) {
+            lpa.heap.push(Reverse((self.lpa_key(lpa, &s), s)));
+        }
+    }
+
+    fn lpa_compute(&self, lpa: &mut Lpa) -> Option<i32> {
+        lpa.expanded = 0;
+        loop {
+            // Drop stale entries off the top
+            let Some(Reverse((key, s))) = lpa.heap.peek().copied() else {
+                break;
+            };
+            if lpa.g(&s) == lpa.rhs(&s) || key != self.lpa_key(lpa, &s) {
+                lpa.heap.pop();
+                continue;
+            }
+            // Carry on through ties with the goal, as the end cells step into it at
+            // zero cost and share its key
+            let goal_key = self.lpa_key(lpa, &State::Goal);
+            if key > goal_key && lpa.rhs(&State::Goal) == lpa.g(&State::Goal) {
+                break;
+            }
+            lpa.heap.pop();
+            lpa.expanded += 1;
+
+            if lpa.g(&s) > lpa.rhs(&s) {
+                // Overconsistent, found a better way. Settle it.
+                lpa.g.insert(s, lpa.rhs(&s));
+            } else {
+                // Underconsistent, the old way got worse. Reset and let it be recalculated.
+                lpa.g.insert(s, i32::MAX);
+                self.lpa_update_state(lpa, s);
+            }
+            for (next, _) in self.lpa_successors(&s) {
+                self.lpa_update_state(lpa, next);
+            }
+        }
+        match lpa.g(&State::Goal) {
+            i32::MAX => None,
+            cost => Some(cost),
+        }
+    }
+
+    // Full LPA* search from scratch. Same result as a_star, but leaves the search
+    // state on the graph for lpa_add_wall and lpa_remove_wall to repair.
+    pub fn lpa_star(&mut self) -> Option<i32> {
+        let mut lpa = Lpa::default();
+        let start = self.lpa_start();
+        lpa.rhs.insert(start, 0);
+        lpa.heap.push(Reverse((self.lpa_key(&lpa, &start), start)));
+        let res = self.lpa_compute(&mut lpa);
+        self.lpa = Some(lpa);
+        res
+    }
+
+    // Add a wall and replan. None if the end is now cut off.
+    pub fn lpa_add_wall(&mut self, pos: Point<i32>) -> Option<i32> {
+        let Some(mut lpa) = self.lpa.take() else {
+            // Never searched, so nothing to repair
+            self.add_wall(pos);
+            return self.lpa_star();
+        };
+        // Whoever could step out of pos loses that way in
+        let neighbours = self.adjacency_list.get(&pos).cloned().unwrap_or_default();
+        if self.add_wall(pos) {
+            for h in Heading::ALL {
+                let s = State::Cell(pos, h);
+                lpa.g.remove(&s);
+                lpa.rhs.remove(&s);
+            }
+            for n in neighbours {
+                for h in Heading::ALL {
+                    self.lpa_update_state(&mut lpa, State::Cell(n, h));
+                }
+            }
+        }
+        let res = self.lpa_compute(&mut lpa);
+        self.lpa = Some(lpa);
+        res
+    }
+
+    // Remove a wall and replan. None if the end is still cut off.
+    pub fn lpa_remove_wall(&mut self, pos: Point<i32>) -> Option<i32> {
+        let Some(mut lpa) = self.lpa.take() else {
+            self.remove_wall(pos);
+            return self.lpa_star();
+        };
+        if self.remove_wall(pos) {
+            // The new cell, and everyone it can now step into
+            for h in Heading::ALL {
+                self.lpa_update_state(&mut lpa, State::Cell(pos, h));
+            }
+            for n in self.adjacency_list[&pos].clone() {
+                for h in Heading::ALL {
+                    self.lpa_update_state(&mut lpa, State::Cell(n, h));
+                }
+            }
+        }
+        let res = self.lpa_compute(&mut lpa);
+        self.lpa = Some(lpa);
+        res
+    }
+}
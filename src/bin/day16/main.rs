@@ -4,12 +4,19 @@ mod dfs;
 mod dijkstra;
 mod visuals;
 mod floyd_warshal;
+mod lpa_star;
+mod path_result;
+mod waypoints;
+mod yen;
 
 fn main() {}
 
 #[cfg(test)]
 mod tests {
-    use crate::day16_graph::Graph;
+    use crate::day16_graph::{Graph, Heading};
+    use crate::path_result::PathResult;
+    use aocutils::point::Point;
+    use itertools::Itertools;
 
     #[test]
     fn test_part_one_test_a() {
@@ -123,4 +130,282 @@ mod tests {
         dbg!(res);
         let _ = graph.dijkstra_plot(false).unwrap();
     }
+
+    // Incremental replanning
+
+    #[test]
+    fn test_lpa_star_matches_dijkstra() {
+        for (file, expected) in [
+            ("src/bin/day16/data/day16_test_a.txt", 7036),
+            ("src/bin/day16/data/day16_test_b.txt", 11048),
+        ] {
+            let mut graph = Graph::new(file);
+            assert_eq!(graph.lpa_star(), Some(expected));
+        }
+        // Turning right round isn't allowed here, the answers still agree
+        for file in [
+            "src/bin/day16/data/day16_test_a.txt",
+            "src/bin/day16/data/day16_test_b.txt",
+        ] {
+            let mut graph = Graph::new(file);
+            assert_eq!(graph.lpa_star(), Graph::new(file).dijkstra(), "{}", file);
+        }
+    }
+
+    #[test]
+    fn test_lpa_star_matches_dijkstra_data() {
+        let file = "src/bin/day16/data/day16_data.txt";
+        let mut graph = Graph::new(file);
+        assert_eq!(graph.lpa_star(), Graph::new(file).dijkstra());
+    }
+
+    #[test]
+    fn test_lpa_star_add_remove_each_wall() {
+        // Wall off each open cell in turn. The repaired answer must agree with a
+        // search from scratch on the changed maze, and come back once the wall goes.
+        let file = "src/bin/day16/data/day16_test_a.txt";
+        let mut graph = Graph::new(file);
+        assert_eq!(graph.lpa_star(), Some(7036));
+        let mut incremental = 0;
+        let mut from_scratch = 0;
+        let mut cells: Vec<Point<i32>> = graph.adjacency_list.keys().copied().collect();
+        cells.sort();
+        for pos in cells {
+            if pos == graph.start || pos == graph.end {
+                assert!(!graph.add_wall(pos));
+                continue;
+            }
+            let res = graph.lpa_add_wall(pos);
+            incremental += graph.lpa.as_ref().unwrap().expanded;
+
+            let mut fresh = Graph::new(file);
+            fresh.add_wall(pos);
+            assert_eq!(res, fresh.lpa_star(), "wall at {:?}", pos);
+            from_scratch += fresh.lpa.as_ref().unwrap().expanded;
+
+            assert_eq!(graph.lpa_remove_wall(pos), Some(7036), "wall at {:?}", pos);
+        }
+        dbg!(incremental, from_scratch);
+        assert!(incremental < from_scratch);
+    }
+
+    #[test]
+    fn test_lpa_star_cut_off() {
+        // Box in the end, then let it out again
+        let mut graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        assert_eq!(graph.lpa_star(), Some(7036));
+        let around_end = graph.adjacency_list[&graph.end].clone();
+        let mut res = Some(0);
+        for pos in &around_end {
+            res = graph.lpa_add_wall(*pos);
+        }
+        assert_eq!(res, None);
+        for pos in &around_end {
+            res = graph.lpa_remove_wall(*pos);
+        }
+        assert_eq!(res, Some(7036));
+    }
+
+    #[test]
+    fn test_lpa_star_after_plain_wall_change() {
+        // A wall changed behind LPA*'s back mustn't leave it repairing stale costs
+        let file = "src/bin/day16/data/day16_test_a.txt";
+        let mut graph = Graph::new(file);
+        assert_eq!(graph.lpa_star(), Some(7036));
+        let mut cells: Vec<Point<i32>> = graph.adjacency_list.keys().copied().collect();
+        cells.sort();
+        let pos = *cells
+            .iter()
+            .find(|pos| **pos != graph.start && **pos != graph.end)
+            .unwrap();
+        assert!(graph.add_wall(pos));
+        assert!(graph.lpa.is_none());
+        let around_end = graph.adjacency_list[&graph.end][0];
+        let res = graph.lpa_add_wall(around_end);
+
+        let mut fresh = Graph::new(file);
+        fresh.add_wall(pos);
+        fresh.add_wall(around_end);
+        assert_eq!(res, fresh.lpa_star());
+        assert!(graph.remove_wall(pos));
+        assert!(graph.lpa.is_none());
+    }
+
+    // Waypoints
+
+    #[test]
+    fn test_waypoint_route_no_waypoints() {
+        let graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        let route = graph.best_waypoint_route(9).unwrap();
+        assert_eq!(route.cost, 7036);
+        assert_eq!(route.path.first(), Some(&graph.start));
+        assert_eq!(route.path.last(), Some(&graph.end));
+    }
+
+    #[test]
+    fn test_waypoint_route_fixed_order() {
+        let mut graph = Graph::new("src/bin/day16/data/day16_waypoints.txt");
+        assert_eq!(graph.waypoints.len(), 3);
+        let route = graph.waypoint_route(&[0, 1, 2]).unwrap();
+        // Visits 1, 2, 3 in that order along the path
+        let visits: Vec<usize> = route
+            .path
+            .iter()
+            .filter_map(|p| graph.waypoints.iter().position(|wp| wp == p))
+            .collect();
+        assert_eq!(visits, vec![0, 1, 2]);
+        // One step at a time
+        for pair in route.path.windows(2) {
+            assert_eq!(
+                (pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs(),
+                1
+            );
+        }
+        graph.waypoint_plot(&route).unwrap();
+    }
+
+    #[test]
+    fn test_waypoint_route_best_order() {
+        let graph = Graph::new("src/bin/day16/data/day16_waypoints.txt");
+        let exact = graph.best_waypoint_route(9).unwrap();
+        let brute_force = (0..3)
+            .permutations(3)
+            .filter_map(|order| graph.waypoint_route(&order))
+            .map(|route| route.cost)
+            .min()
+            .unwrap();
+        assert_eq!(exact.cost, brute_force);
+        assert_eq!(graph.waypoint_route(&exact.order).unwrap().cost, exact.cost);
+
+        // Heuristic can't beat it, and still calls everywhere
+        let heuristic = graph.best_waypoint_route(0).unwrap();
+        assert!(heuristic.cost >= exact.cost);
+        assert_eq!(
+            heuristic.order.iter().sorted().collect::<Vec<_>>(),
+            vec![&0, &1, &2]
+        );
+    }
+
+    // K shortest paths
+
+    #[test]
+    fn test_k_shortest_paths_test_a() {
+        let mut graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        let paths = graph.k_shortest_paths(graph.start, graph.end, 5);
+        assert_eq!(paths.len(), 5);
+        assert_eq!(paths[0].0, 7036);
+        for (cost, path) in &paths {
+            assert_eq!(path.first(), Some(&graph.start));
+            assert_eq!(path.last(), Some(&graph.end));
+            assert!(path.iter().all_unique());
+            assert_eq!(graph.path_cost(path, Heading::East), *cost);
+        }
+        assert!(paths.iter().map(|(cost, _)| cost).is_sorted());
+        assert!(paths.iter().map(|(_, path)| path).all_unique());
+        // The three best paths part two finds, then dearer ones
+        assert!(paths[..3].iter().all(|(cost, _)| *cost == 7036));
+        assert!(paths[3].0 > 7036);
+        graph.k_shortest_plot(&paths).unwrap();
+    }
+
+    #[test]
+    fn test_k_shortest_paths_from_wall() {
+        let graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        assert!(graph
+            .k_shortest_paths(Point { x: 0, y: 0 }, graph.end, 3)
+            .is_empty());
+        assert!(graph
+            .k_shortest_paths(graph.start, Point { x: 0, y: 0 }, 3)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_k_shortest_paths_all_simple_paths() {
+        // Small enough to list every simple path and sort them
+        let graph = Graph::new("src/bin/day16/data/day16_waypoints.txt");
+        let mut all = Vec::new();
+        let mut path = vec![graph.start];
+        simple_paths(&graph, &mut path, &mut all);
+        all.sort();
+
+        let paths = graph.k_shortest_paths(graph.start, graph.end, all.len() + 1);
+        assert_eq!(paths.len(), all.len());
+        let costs: Vec<i32> = paths.iter().map(|(cost, _)| *cost).collect();
+        assert_eq!(costs, all);
+    }
+
+    fn simple_paths(graph: &Graph, path: &mut Vec<Point<i32>>, costs: &mut Vec<i32>) {
+        let at = *path.last().unwrap();
+        if at == graph.end {
+            costs.push(graph.path_cost(path, Heading::East));
+            return;
+        }
+        for next in graph.adjacency_list[&at].clone() {
+            if !path.contains(&next) {
+                path.push(next);
+                simple_paths(graph, path, costs);
+                path.pop();
+            }
+        }
+    }
+
+    // Path results
+
+    #[test]
+    fn test_path_result_test_a() {
+        let mut graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        let res = graph.dijkstra().unwrap();
+        let path = graph.path_result().unwrap();
+        println!("{}", path);
+        assert_eq!(path.cost(), res);
+        assert_eq!((path.steps, path.turns), (36, 7));
+        assert_eq!(path.path.first(), Some(&graph.start));
+        assert_eq!(path.path.last(), Some(&graph.end));
+        assert_eq!(graph.simulate(&path.script), Ok(path));
+    }
+
+    #[test]
+    fn test_path_result_test_b() {
+        let mut graph = Graph::new("src/bin/day16/data/day16_test_b.txt");
+        let res = graph.a_star().unwrap();
+        let path = graph.path_result().unwrap();
+        println!("{}", path);
+        assert_eq!(path.cost(), res);
+        assert_eq!((path.steps, path.turns), (48, 11));
+        assert_eq!(graph.simulate(&path.script), Ok(path));
+    }
+
+    #[test]
+    fn test_path_result_script() {
+        let graph = Graph::new("src/bin/day16/data/day16_waypoints.txt");
+        // Into the dead end at 3 and back out needs turning right round
+        let path = graph.waypoint_route(&[2]).unwrap().path;
+        let res = PathResult::from_path(path, Heading::East);
+        assert!(res.script.contains("R R"));
+        assert_eq!(graph.simulate(&res.script), Ok(res));
+
+        assert_eq!(
+            graph.simulate("F2 R F2").unwrap().path.last(),
+            Some(&Point { x: 3, y: 3 })
+        );
+        assert_eq!(graph.simulate("R F2").unwrap().cost(), 1002);
+        assert!(graph.simulate("F3").is_err());
+        assert!(graph.simulate("F2 X").is_err());
+        // Only whole steps forward
+        assert_eq!(
+            graph.simulate("F-3"),
+            Err("Bad move \"F-3\" at token 1".to_string())
+        );
+        assert!(graph.simulate("F0").is_err());
+    }
+
+    #[test]
+    fn test_path_result_k_shortest() {
+        let graph = Graph::new("src/bin/day16/data/day16_test_a.txt");
+        for (cost, path) in graph.k_shortest_paths(graph.start, graph.end, 3) {
+            let res = PathResult::from_path(path, Heading::East);
+            assert_eq!(res.cost(), cost);
+            assert_eq!(graph.simulate(&res.script), Ok(res));
+        }
+    }
 }
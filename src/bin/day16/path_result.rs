@@ -0,0 +1,140 @@
+use crate::day16_graph::{Graph, Heading};
+use aocutils::point::Point;
+use std::fmt;
+
+// A found route told as the Reindeer would walk it. Every forward step costs 1 and
+// every 90 degree turn on the spot costs 1000, so the total is steps + 1000 * turns.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathResult {
+    // start to end, including both
+    pub path: Vec<Point<i32>>,
+    pub steps: i32,
+    pub turns: i32,
+    // movement script, e.g. "F3 R F5 L F1"
+    pub script: String,
+}
+
+impl PathResult {
+    // Walk the path from its first point, starting facing `facing`
+    pub fn from_path(path: Vec<Point<i32>>, facing: Heading) -> Self {
+        let mut steps = 0;
+        let mut turns = 0;
+        let mut script: Vec<String> = Vec::new();
+        let mut heading = facing;
+        let mut run = 0;
+        for pair in path.windows(2) {
+            let next = Heading::between(pair[0], pair[1]);
+            if next != heading {
+                if run > 0 {
+                    script.push(format!("F{}", run));
+                    run = 0;
+                }
+                if next == heading.right() {
+                    script.push("R".to_string());
+                    turns += 1;
+                } else if next == heading.left() {
+                    script.push("L".to_string());
+                    turns += 1;
+                } else {
+                    // right round
+                    script.push("R R".to_string());
+                    turns += 2;
+                }
+                heading = next;
+            }
+            run += 1;
+            steps += 1;
+        }
+        if run > 0 {
+            script.push(format!("F{}", run));
+        }
+        Self {
+            path,
+            steps,
+            turns,
+            script: script.join(" "),
+        }
+    }
+
+    pub fn step_cost(&self) -> i32 {
+        self.steps
+    }
+
+    pub fn turn_cost(&self) -> i32 {
+        self.turns * 1000
+    }
+
+    pub fn cost(&self) -> i32 {
+        self.step_cost() + self.turn_cost()
+    }
+}
+
+impl fmt::Display for PathResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} = {} steps x 1 + {} turns x 1000",
+            self.cost(),
+            self.step_cost(),
+            self.turns
+        )?;
+        write!(f, "{}", self.script)
+    }
+}
+
+impl Graph {
+    // The path found by the last dijkstra or a_star run, None if the end wasn't reached
+    pub fn path_result(&mut self) -> Option<PathResult> {
+        self.node_list[&self.end].came_from?;
+        let mut path = self.show_path();
+        path.reverse();
+        path.push(self.end);
+        Some(PathResult::from_path(path, Heading::East))
+    }
+
+    // Run a movement script from S facing east, counting the cost as it goes. F<n>
+    // walks n > 0 cells forward, R and L turn on the spot. Fails on anything else, or on
+    // walking into a wall.
+    pub fn simulate(&self, script: &str) -> Result<PathResult, String> {
+        let mut heading = Heading::East;
+        let mut pos = self.start;
+        let mut path = vec![pos];
+        let mut steps = 0;
+        let mut turns = 0;
+        let tokens: Vec<&str> = script.split_whitespace().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            match *token {
+                "R" => {
+                    heading = heading.right();
+                    turns += 1;
+                }
+                "L" => {
+                    heading = heading.left();
+                    turns += 1;
+                }
+                _ => {
+                    let n: u32 = token
+                        .strip_prefix('F')
+                        .and_then(|n| n.parse().ok())
+                        .filter(|n| *n > 0)
+                        .ok_or(format!("Bad move {:?} at token {}", token, i + 1))?;
+                    for _ in 0..n {
+                        pos = heading.step(pos);
+                        if !self.adjacency_list.contains_key(&pos) {
+                            return Err(format!("Hit a wall at {:?} on token {}", pos, i + 1));
+                        }
+                        path.push(pos);
+                    }
+                    steps += n as i32;
+                }
+            }
+        }
+        Ok(PathResult {
+            path,
+            steps,
+            turns,
+            script: tokens.join(" "),
+        })
+    }
+}
@@ -1,4 +1,6 @@
 use crate::day16_graph::{Graph, Node};
+use crate::waypoints::WaypointRoute;
+use aocutils::point::Point;
 use plotters::backend::BitMapBackend;
 use plotters::coord::types::RangedCoordi32;
 use plotters::prelude::full_palette::GREY;
@@ -222,4 +224,112 @@ impl Graph {
         root_area.present()?;
         Ok(())
     }
+
+    pub fn waypoint_plot(
+        &mut self,
+        route: &WaypointRoute,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let out = format!("{}_{:06}{}", OUTPUT_FILENAME, self.plot_sequence, ".png");
+        let root_area = BitMapBackend::new(&out, (1024, 1024)).into_drawing_area();
+
+        root_area.fill(&WHITE).unwrap();
+        let end_x = self.xrange.end;
+        let end_y = self.yrange.end;
+        let root_area =
+            root_area.apply_coord_spec(Cartesian2d::<RangedCoordi32, RangedCoordi32>::new(
+                0..end_x,
+                0..end_y,
+                (0..1024, 0..1024),
+            ));
+
+        let block_side = 1024 / self.yrange.end + 1;
+        let block = |x: i32, y: i32, c: RGBColor| {
+            return EmptyElement::at((x, y))
+                + Rectangle::new(
+                    [(0, 0), (block_side, block_side)],
+                    ShapeStyle::from(&c).filled(),
+                );
+        };
+        // waypoints labelled with their place in the visiting order
+        let waypoint_block = |x: i32, y: i32, visit: usize| {
+            EmptyElement::at((x, y))
+                + Rectangle::new(
+                    [(0, 0), (block_side, block_side)],
+                    ShapeStyle::from(&BLUE).filled(),
+                )
+                + Text::new(
+                    format!("{}", visit),
+                    (block_side / 4, block_side / 4),
+                    ("sans-serif", block_side / 2).into_font().color(&WHITE),
+                )
+        };
+
+        for pos in self.walls.clone() {
+            root_area.draw(&block(pos.x, pos.y, GREY))?;
+        }
+        for pos in &route.path {
+            root_area.draw(&block(pos.x, pos.y, BLACK))?;
+        }
+        for (visit, i) in route.order.iter().enumerate() {
+            let pos = self.waypoints[*i];
+            root_area.draw(&waypoint_block(pos.x, pos.y, visit + 1))?;
+        }
+        root_area.draw(&block(self.start.x, self.start.y, RED))?;
+        root_area.draw(&block(self.end.x, self.end.y, GREEN))?;
+
+        root_area.present()?;
+        Ok(())
+    }
+
+    // Overlay of the k shortest paths, best first at full size with each next one
+    // drawn smaller on top, so shared stretches show every colour
+    pub fn k_shortest_plot(
+        &mut self,
+        paths: &[(i32, Vec<Point<i32>>)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let out = format!("{}_{:06}{}", OUTPUT_FILENAME, self.plot_sequence, ".png");
+        let root_area = BitMapBackend::new(&out, (1024, 1024)).into_drawing_area();
+
+        root_area.fill(&WHITE).unwrap();
+        let end_x = self.xrange.end;
+        let end_y = self.yrange.end;
+        let root_area =
+            root_area.apply_coord_spec(Cartesian2d::<RangedCoordi32, RangedCoordi32>::new(
+                0..end_x,
+                0..end_y,
+                (0..1024, 0..1024),
+            ));
+
+        let block_side = 1024 / self.yrange.end + 1;
+        let block = |x: i32, y: i32, c: RGBColor| {
+            return EmptyElement::at((x, y))
+                + Rectangle::new(
+                    [(0, 0), (block_side, block_side)],
+                    ShapeStyle::from(&c).filled(),
+                );
+        };
+        let inset = block_side / (2 * paths.len().max(1) as i32);
+        let path_block = |x: i32, y: i32, i: usize| {
+            let gap = inset * i as i32;
+            EmptyElement::at((x, y))
+                + Rectangle::new(
+                    [(gap, gap), (block_side - gap, block_side - gap)],
+                    ShapeStyle::from(&Palette99::pick(i)).filled(),
+                )
+        };
+
+        for pos in self.walls.clone() {
+            root_area.draw(&block(pos.x, pos.y, GREY))?;
+        }
+        for (i, (_, path)) in paths.iter().enumerate() {
+            for pos in path {
+                root_area.draw(&path_block(pos.x, pos.y, i))?;
+            }
+        }
+        root_area.draw(&block(self.start.x, self.start.y, RED))?;
+        root_area.draw(&block(self.end.x, self.end.y, GREEN))?;
+
+        root_area.present()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,243 @@
+use crate::day16_graph::{Graph, Heading};
+use crate::dijkstra::{HeadingState, HeadingTree};
+use aocutils::point::Point;
+use std::collections::HashMap;
+
+// Routes from S to E that call at every waypoint on the way. Built on the
+// shortest paths between each pair of waypoints, with the heading on arrival kept
+// as part of the state so the turn costs stay exact across legs.
+
+#[derive(Debug, Clone)]
+pub struct WaypointRoute {
+    pub cost: i32,
+    // indices into Graph::waypoints, in visiting order
+    pub order: Vec<usize>,
+    // start to end, including both
+    pub path: Vec<Point<i32>>,
+}
+
+// Pairwise shortest paths. A tree from S facing east, and one from each waypoint
+// for every heading it could have arrived with.
+struct Legs {
+    trees: HashMap<HeadingState, HeadingTree>,
+}
+
+impl Legs {
+    fn cost(&self, from: HeadingState, to: HeadingState) -> Option<i32> {
+        self.trees[&from].get(&to).map(|(c, _)| *c)
+    }
+}
+
+impl Graph {
+    fn legs(&self) -> Legs {
+        let mut trees = HashMap::new();
+        let from_start = (self.start, Heading::East);
+        trees.insert(from_start, self.heading_dijkstra(from_start));
+        for wp in &self.waypoints {
+            for h in Heading::ALL {
+                trees.insert((*wp, h), self.heading_dijkstra((*wp, h)));
+            }
+        }
+        Legs { trees }
+    }
+
+    // Cheapest route visiting the waypoints in the given order. Each leg can arrive
+    // facing any way, so keep the best cost per arrival heading as we go.
+    pub fn waypoint_route(&self, order: &[usize]) -> Option<WaypointRoute> {
+        let legs = self.legs();
+        self.route_in_order(&legs, order)
+    }
+
+    fn route_in_order(&self, legs: &Legs, order: &[usize]) -> Option<WaypointRoute> {
+        // One stage per stop: arrival heading -> (cost so far, heading at the stop before)
+        let from_start = (self.start, Heading::East);
+        let mut stages: Vec<HashMap<Heading, (i32, Heading)>> =
+            vec![HashMap::from([(Heading::East, (0, Heading::East))])];
+        let mut at = self.start;
+        for wp in order.iter().map(|i| self.waypoints[*i]) {
+            let mut next = HashMap::new();
+            for (h, (cost, _)) in stages.last().unwrap() {
+                for h2 in Heading::ALL {
+                    if let Some(leg) = legs.cost((at, *h), (wp, h2)) {
+                        if next.get(&h2).is_none_or(|(c, _)| cost + leg < *c) {
+                            next.insert(h2, (cost + leg, *h));
+                        }
+                    }
+                }
+            }
+            stages.push(next);
+            at = wp;
+        }
+        // Last leg to E, any heading will do
+        let (cost, last_h, end_h) = stages
+            .last()
+            .unwrap()
+            .iter()
+            .flat_map(|(h, (cost, _))| {
+                Heading::ALL.iter().filter_map(move |h2| {
+                    legs.cost((at, *h), (self.end, *h2))
+                        .map(|leg| (cost + leg, *h, *h2))
+                })
+            })
+            .min()?;
+
+        // Back through the stages for the heading at each waypoint
+        let mut headings = Vec::new();
+        let mut h = last_h;
+        for stage in stages.iter().skip(1).rev() {
+            headings.push(h);
+            h = stage[&h].1;
+        }
+        headings.reverse();
+
+        let mut stops = vec![from_start];
+        stops.extend(order.iter().map(|i| self.waypoints[*i]).zip(headings));
+        stops.push((self.end, end_h));
+        Some(WaypointRoute {
+            cost,
+            order: order.to_vec(),
+            path: Self::join_legs(legs, &stops),
+        })
+    }
+
+    fn join_legs(legs: &Legs, stops: &[HeadingState]) -> Vec<Point<i32>> {
+        let mut path = vec![stops[0].0];
+        for pair in stops.windows(2) {
+            let leg = Self::heading_path(&legs.trees[&pair[0]], pair[1]);
+            // first point of each leg is the last of the one before
+            path.extend(leg.into_iter().skip(1));
+        }
+        path
+    }
+
+    // Cheapest route through every waypoint in any order. Exact Held-Karp up to
+    // `exact_up_to` waypoints, beyond that nearest neighbour tidied up with 2-opt.
+    pub fn best_waypoint_route(&self, exact_up_to: usize) -> Option<WaypointRoute> {
+        let legs = self.legs();
+        if self.waypoints.len() <= exact_up_to {
+            self.held_karp(&legs)
+        } else {
+            self.nearest_neighbour_2opt(&legs)
+        }
+    }
+
+    fn held_karp(&self, legs: &Legs) -> Option<WaypointRoute> {
+        let n = self.waypoints.len();
+        if n == 0 {
+            return self.route_in_order(legs, &[]);
+        }
+        let full = (1usize << n) - 1;
+        // dp[(visited, last, heading)] = (cost, previous (last, heading) or None for S)
+        let mut dp: HashMap<(usize, usize, Heading), (i32, Option<(usize, Heading)>)> =
+            HashMap::new();
+        for (i, wp) in self.waypoints.iter().enumerate() {
+            for h in Heading::ALL {
+                if let Some(c) = legs.cost((self.start, Heading::East), (*wp, h)) {
+                    dp.insert((1 << i, i, h), (c, None));
+                }
+            }
+        }
+        // Subsets in increasing order, so every smaller subset is done first
+        for visited in 1..=full {
+            for last in (0..n).filter(|i| visited & (1 << i) != 0) {
+                for h in Heading::ALL {
+                    let Some((cost, _)) = dp.get(&(visited, last, h)).copied() else {
+                        continue;
+                    };
+                    for next in (0..n).filter(|i| visited & (1 << i) == 0) {
+                        for h2 in Heading::ALL {
+                            let Some(leg) =
+                                legs.cost((self.waypoints[last], h), (self.waypoints[next], h2))
+                            else {
+                                continue;
+                            };
+                            let key = (visited | (1 << next), next, h2);
+                            if dp.get(&key).is_none_or(|(c, _)| cost + leg < *c) {
+                                dp.insert(key, (cost + leg, Some((last, h))));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Close off to E and pick the cheapest
+        let mut best: Option<(i32, usize, Heading)> = None;
+        for last in 0..n {
+            for h in Heading::ALL {
+                let Some((cost, _)) = dp.get(&(full, last, h)) else {
+                    continue;
+                };
+                for h2 in Heading::ALL {
+                    if let Some(leg) = legs.cost((self.waypoints[last], h), (self.end, h2)) {
+                        if best.is_none_or(|(b, _, _)| cost + leg < b) {
+                            best = Some((cost + leg, last, h));
+                        }
+                    }
+                }
+            }
+        }
+        let (_, mut last, mut h) = best?;
+        let mut visited = full;
+        let mut order = Vec::new();
+        loop {
+            order.push(last);
+            match dp[&(visited, last, h)].1 {
+                Some((prev, prev_h)) => {
+                    visited &= !(1 << last);
+                    last = prev;
+                    h = prev_h;
+                }
+                None => break,
+            }
+        }
+        order.reverse();
+        self.route_in_order(legs, &order)
+    }
+
+    fn nearest_neighbour_2opt(&self, legs: &Legs) -> Option<WaypointRoute> {
+        // Ignore headings for the greedy pick, cheapest way in whichever way we face
+        let n = self.waypoints.len();
+        let min_leg = |from: Point<i32>, to: Point<i32>| -> Option<i32> {
+            Heading::ALL
+                .iter()
+                .flat_map(|h| Heading::ALL.iter().map(move |h2| (*h, *h2)))
+                .filter(|(h, _)| legs.trees.contains_key(&(from, *h)))
+                .filter_map(|(h, h2)| legs.cost((from, h), (to, h2)))
+                .min()
+        };
+        let mut order = Vec::new();
+        let mut at = self.start;
+        let mut left: Vec<usize> = (0..n).collect();
+        while !left.is_empty() {
+            let (i, _) = left
+                .iter()
+                .enumerate()
+                .filter_map(|(i, wp)| min_leg(at, self.waypoints[*wp]).map(|c| (i, c)))
+                .min_by_key(|(_, c)| *c)?;
+            at = self.waypoints[left[i]];
+            order.push(left.remove(i));
+        }
+
+        // 2-opt, reversing any stretch of the order that makes it cheaper, until
+        // nothing does
+        let mut best = self.route_in_order(legs, &order)?;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..n {
+                for j in i + 1..n {
+                    let mut candidate = best.order.clone();
+                    candidate[i..=j].reverse();
+                    if let Some(route) = self.route_in_order(legs, &candidate) {
+                        if route.cost < best.cost {
+                            best = route;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+        }
+        Some(best)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::day16_graph::{Graph, Heading};
+use crate::dijkstra::{HeadingState, HeadingTree};
+use aocutils::point::Point;
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+// Yen's K shortest loopless paths. Each next best path leaves one of the paths
+// already found at some spur node, so for each node along the last path found,
+// keep the root up to it and search for a new way on from there with the edges
+// the earlier paths took out of that root blocked off.
+
+impl Graph {
+    // Up to k paths from `from` to `to`, cheapest first, starting facing east as the
+    // Reindeer does. Each path runs from `from` to `to` inclusive with its cost.
+    pub fn k_shortest_paths(
+        &self,
+        from: Point<i32>,
+        to: Point<i32>,
+        k: usize,
+    ) -> Vec<(i32, Vec<Point<i32>>)> {
+        let mut found: Vec<(i32, Vec<Point<i32>>)> = Vec::new();
+        if k == 0 {
+            return found;
+        }
+        let no_nodes = HashSet::new();
+        let no_edges = HashSet::new();
+        match self.spur_dijkstra((from, Heading::East), to, &no_nodes, &no_edges) {
+            Some(first) => found.push(first),
+            None => return found,
+        }
+
+        // Candidates, min-heap on cost then path so ties come out the same every time
+        let mut candidates: BinaryHeap<Reverse<(i32, Vec<Point<i32>>)>> = BinaryHeap::new();
+        let mut seen: HashSet<Vec<Point<i32>>> = HashSet::from([found[0].1.clone()]);
+
+        while found.len() < k {
+            let last = found.last().unwrap().1.clone();
+            for i in 0..last.len() - 1 {
+                let spur = last[i];
+                let root = &last[..=i];
+                let heading = if i == 0 {
+                    Heading::East
+                } else {
+                    Heading::between(last[i - 1], spur)
+                };
+
+                // Don't go the way any path sharing this root already went
+                let mut blocked_edges = HashSet::new();
+                for (_, path) in &found {
+                    if path.len() > i + 1 && path[..=i] == *root {
+                        blocked_edges.insert((path[i], path[i + 1]));
+                    }
+                }
+                // Or back through the root, which would make a loop
+                let blocked_nodes: HashSet<Point<i32>> = root[..i].iter().copied().collect();
+
+                if let Some((spur_cost, spur_path)) =
+                    self.spur_dijkstra((spur, heading), to, &blocked_nodes, &blocked_edges)
+                {
+                    let mut path = root[..i].to_vec();
+                    path.extend(spur_path);
+                    // The spur can only come back on itself facing another way, which
+                    // is never cheaper, but make sure
+                    if path.iter().all_unique() && seen.insert(path.clone()) {
+                        let cost = self.path_cost(root, Heading::East) + spur_cost;
+                        candidates.push(Reverse((cost, path)));
+                    }
+                }
+            }
+            match candidates.pop() {
+                Some(Reverse(next)) => found.push(next),
+                // No more ways through
+                None => break,
+            }
+        }
+        found
+    }
+
+    // Cost of walking a path one step at a time, starting facing `facing`
+    pub(crate) fn path_cost(&self, path: &[Point<i32>], facing: Heading) -> i32 {
+        let mut cost = 0;
+        let mut heading = facing;
+        for pair in path.windows(2) {
+            let next = Heading::between(pair[0], pair[1]);
+            cost += heading.reversing_cost(next);
+            heading = next;
+        }
+        cost
+    }
+
+    // Heading aware Dijkstra from one state to a position, avoiding some nodes and
+    // edges. Stops as soon as `to` comes off the heap.
+    fn spur_dijkstra(
+        &self,
+        from: HeadingState,
+        to: Point<i32>,
+        blocked_nodes: &HashSet<Point<i32>>,
+        blocked_edges: &HashSet<(Point<i32>, Point<i32>)>,
+    ) -> Option<(i32, Vec<Point<i32>>)> {
+        let mut best: HeadingTree = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        best.insert(from, (0, None));
+        heap.push(Reverse((0, from)));
+
+        while let Some(Reverse((cost, (position, heading)))) = heap.pop() {
+            if position == to {
+                return Some((cost, Self::heading_path(&best, (position, heading))));
+            }
+            if cost > best[&(position, heading)].0 {
+                continue;
+            }
+            // Nowhere to go from a wall
+            let Some(edges) = self.adjacency_list.get(&position) else {
+                continue;
+            };
+            for node in edges {
+                if blocked_nodes.contains(node) || blocked_edges.contains(&(position, *node)) {
+                    continue;
+                }
+                let next_heading = Heading::between(position, *node);
+                let next_cost = cost + heading.reversing_cost(next_heading);
+                let next = (*node, next_heading);
+                if best.get(&next).is_none_or(|(c, _)| next_cost < *c) {
+                    best.insert(next, (next_cost, Some((position, heading))));
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+        // Goal not reachable
+        None
+    }
+}
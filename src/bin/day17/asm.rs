@@ -0,0 +1,159 @@
+use crate::vm::{Opcode, OperandKind, VM};
+use std::collections::HashMap;
+use std::fmt;
+
+// Assembler for the source the disassembler writes, so programs can be written by
+// name rather than as opcode/operand pairs.
+//
+//     .register A 2024    ; initial register values, default 0
+//     start:              ; label, on its own or in front of an instruction
+//         adv 3           ; combo operand: 0-3, A, B, C (or 7, reserved)
+//         out A
+//         jnz start       ; jump to a label or an address
+//     .raw 9 1            ; values copied into the program as is
+//
+// Output is the Register A/B/C + Program: format that VM::new reads.
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AsmError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+fn error(line: usize, message: String) -> AsmError {
+    AsmError { line, message }
+}
+
+pub(crate) fn assemble(source: &str) -> Result<String, AsmError> {
+    Ok(assemble_vm(source)?.to_file_format())
+}
+
+pub(crate) fn assemble_vm(source: &str) -> Result<VM, AsmError> {
+    // Strip comments and labels, keeping (line number, label, rest of the line)
+    let mut lines: Vec<(usize, Option<&str>, Vec<&str>)> = Vec::new();
+    for (i, raw) in source.lines().enumerate() {
+        let line = i + 1;
+        let code = raw.split(';').next().unwrap().trim();
+        let (label, rest) = match code.split_once(':') {
+            Some((label, rest)) => {
+                let label = label.trim();
+                if !is_identifier(label) {
+                    return Err(error(line, format!("bad label {:?}", label)));
+                }
+                (Some(label), rest.trim())
+            }
+            None => (None, code),
+        };
+        lines.push((line, label, rest.split_whitespace().collect()));
+    }
+
+    // First pass, where each label lands
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut address = 0;
+    for (line, label, tokens) in &lines {
+        if let Some(label) = label {
+            if labels.insert(label, address).is_some() {
+                return Err(error(*line, format!("label {:?} defined twice", label)));
+            }
+        }
+        match tokens.first() {
+            None => {}
+            Some(&".register") => {}
+            Some(&".raw") => address += tokens.len() - 1,
+            Some(_) => address += 2,
+        }
+    }
+
+    // Second pass, encode
+    let mut vm = VM::parse("");
+    for (line, _, tokens) in &lines {
+        let line = *line;
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+        match *first {
+            ".register" => {
+                let [_, register, value] = tokens[..] else {
+                    return Err(error(
+                        line,
+                        ".register takes a register and a value".to_string(),
+                    ));
+                };
+                let value: u64 = value
+                    .parse()
+                    .map_err(|_| error(line, format!("bad register value {:?}", value)))?;
+                match register {
+                    "A" => vm.reg_a = value,
+                    "B" => vm.reg_b = value,
+                    "C" => vm.reg_c = value,
+                    _ => return Err(error(line, format!("unknown register {:?}", register))),
+                }
+            }
+            ".raw" => {
+                for value in &tokens[1..] {
+                    vm.program.push(
+                        value
+                            .parse()
+                            .map_err(|_| error(line, format!("bad raw value {:?}", value)))?,
+                    );
+                }
+            }
+            mnemonic => {
+                let opcode = Opcode::from_mnemonic(mnemonic)
+                    .ok_or(error(line, format!("unknown instruction {:?}", mnemonic)))?;
+                let [_, operand] = tokens[..] else {
+                    return Err(error(line, format!("{} takes one operand", mnemonic)));
+                };
+                vm.program.push(opcode.code());
+                vm.program
+                    .push(encode_operand(opcode, operand, &labels, line)?);
+            }
+        }
+    }
+    Ok(vm)
+}
+
+fn encode_operand(
+    opcode: Opcode,
+    operand: &str,
+    labels: &HashMap<&str, usize>,
+    line: usize,
+) -> Result<usize, AsmError> {
+    let value = match (opcode.operand_kind(), operand) {
+        (OperandKind::Combo, "A") => 4,
+        (OperandKind::Combo, "B") => 5,
+        (OperandKind::Combo, "C") => 6,
+        (OperandKind::Jump, label) if is_identifier(label) => *labels
+            .get(label)
+            .ok_or(error(line, format!("unknown label {:?}", label)))?,
+        (_, number) => number
+            .parse()
+            .map_err(|_| error(line, format!("bad operand {:?}", number)))?,
+    };
+    if value > 7 {
+        return Err(error(
+            line,
+            format!(
+                "operand {:?} is {}, past what 3 bits can hold",
+                operand, value
+            ),
+        ));
+    }
+    Ok(value)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
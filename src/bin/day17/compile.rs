@@ -0,0 +1,308 @@
+use crate::register::Register;
+use crate::vm::{Opcode, Output, Registers, VmError, VM};
+use rayon::prelude::*;
+use std::fmt;
+
+// The program turned into closures, so searches over A don't decode every
+// instruction again on every step. Each operand is worked out once up front (a
+// literal shift becomes a constant divisor, a combo register becomes a direct read)
+// and the straight runs between jumps become basic blocks:
+//
+//     L0:  bst A, bxl 1, cdv B, bxl 5, bxc, adv 3, out B    one block
+//          jnz L0                                          its exit
+//
+// so going round the loop is one block lookup rather than one match per instruction.
+// Runs the same as the interpreter, errors and step budget included, except that it
+// can't follow a jump to an odd address, which would read the program out of step.
+
+type Op = Box<dyn Fn(&mut Registers, &mut Vec<usize>) -> Result<(), VmError> + Send + Sync>;
+
+enum Exit {
+    // on into the block at this address, or halt if it's past the end
+    Next(usize),
+    // back to target if A != 0, else on to next
+    Jnz { target: usize, next: usize },
+}
+
+struct Block {
+    ops: Vec<Op>,
+    exit: Exit,
+}
+
+pub(crate) struct Compiled {
+    // indexed by address, Some where a block starts
+    blocks: Vec<Option<Block>>,
+    pub(crate) max_steps: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompileError {
+    OddJump { ip: usize, target: usize },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::OddJump { ip, target } => write!(
+                f,
+                "ip {}: jump to odd address {} can't be compiled",
+                ip, target
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl VM {
+    pub(crate) fn compile(&self) -> Result<Compiled, CompileError> {
+        let len = self.program.len();
+        // Where blocks start: the top, every jump target and just after every jump
+        let mut leaders = vec![false; len];
+        if len > 0 {
+            leaders[0] = true;
+        }
+        for ip in (0..len.saturating_sub(1)).step_by(2) {
+            if self.program[ip] == Opcode::Jnz.code() {
+                let target = self.program[ip + 1];
+                if target < len {
+                    if target % 2 != 0 {
+                        return Err(CompileError::OddJump { ip, target });
+                    }
+                    leaders[target] = true;
+                }
+                if ip + 2 < len {
+                    leaders[ip + 2] = true;
+                }
+            }
+        }
+
+        let blocks = (0..len)
+            .map(|start| leaders[start].then(|| self.compile_block(start, &leaders)))
+            .collect();
+        Ok(Compiled {
+            blocks,
+            max_steps: self.max_steps,
+        })
+    }
+
+    fn compile_block(&self, start: usize, leaders: &[bool]) -> Block {
+        let len = self.program.len();
+        let mut ops = Vec::new();
+        let mut ip = start;
+        loop {
+            if ip >= len || (ip != start && leaders[ip]) {
+                return Block {
+                    ops,
+                    exit: Exit::Next(ip),
+                };
+            }
+            let Some(&operand) = self.program.get(ip + 1) else {
+                ops.push(fail(VmError::MissingOperand { ip }));
+                ip += 2;
+                continue;
+            };
+            match Opcode::from_code(self.program[ip]) {
+                Some(Opcode::Jnz) => {
+                    return Block {
+                        ops,
+                        exit: Exit::Jnz {
+                            target: operand,
+                            next: ip + 2,
+                        },
+                    }
+                }
+                Some(opcode) => ops.push(compile_op(ip, opcode, operand)),
+                None => ops.push(fail(VmError::UnknownOpcode {
+                    ip,
+                    opcode: self.program[ip],
+                })),
+            }
+            ip += 2;
+        }
+    }
+}
+
+// The combo registers, read straight off
+fn register(operand: usize) -> fn(&Registers) -> u64 {
+    match operand {
+        4 => |r| r.a,
+        5 => |r| r.b,
+        _ => |r| r.c,
+    }
+}
+
+fn fail(error: VmError) -> Op {
+    Box::new(move |_, _| Err(error))
+}
+
+fn compile_op(ip: usize, opcode: Opcode, operand: usize) -> Op {
+    match opcode {
+        Opcode::Adv => shift_op(ip, operand, |r, v| r.a = v),
+        Opcode::Bdv => shift_op(ip, operand, |r, v| r.b = v),
+        Opcode::Cdv => shift_op(ip, operand, |r, v| r.c = v),
+        Opcode::Bxl => {
+            let literal = operand as u64;
+            Box::new(move |r, _| {
+                r.b ^= literal;
+                Ok(())
+            })
+        }
+        Opcode::Bxc => Box::new(|r, _| {
+            r.b ^= r.c;
+            Ok(())
+        }),
+        Opcode::Bst => match operand {
+            0..=3 => {
+                let value = operand as u64;
+                Box::new(move |r, _| {
+                    r.b = value;
+                    Ok(())
+                })
+            }
+            4..=6 => {
+                let read = register(operand);
+                Box::new(move |r, _| {
+                    r.b = read(r) % 8;
+                    Ok(())
+                })
+            }
+            _ => fail(VmError::ReservedCombo { ip }),
+        },
+        Opcode::Out => match operand {
+            0..=3 => Box::new(move |_, out| {
+                out.push(operand);
+                Ok(())
+            }),
+            4..=6 => {
+                let read = register(operand);
+                Box::new(move |r, out| {
+                    out.push((read(r) % 8) as usize);
+                    Ok(())
+                })
+            }
+            _ => fail(VmError::ReservedCombo { ip }),
+        },
+        // an exit, never an op
+        Opcode::Jnz => unreachable!(),
+    }
+}
+
+// A >> combo, stored by `store`
+fn shift_op(ip: usize, operand: usize, store: fn(&mut Registers, u64)) -> Op {
+    match operand {
+        0..=3 => {
+            let divisor = 1u64 << operand;
+            Box::new(move |r, _| {
+                store(r, r.a / divisor);
+                Ok(())
+            })
+        }
+        4..=6 => {
+            let read = register(operand);
+            Box::new(move |r, _| {
+                store(r, r.a.shift_right(&read(r)));
+                Ok(())
+            })
+        }
+        _ => fail(VmError::ReservedCombo { ip }),
+    }
+}
+
+impl Compiled {
+    // Run from the top, stopping early once `keep_going` says no after a block.
+    // Returns the address it stopped at.
+    fn execute(
+        &self,
+        regs: &mut Registers,
+        output: &mut Vec<usize>,
+        mut keep_going: impl FnMut(&[usize]) -> bool,
+    ) -> Result<usize, VmError> {
+        let len = self.blocks.len();
+        let mut ip = 0;
+        let mut steps = 0;
+        while ip < len {
+            let block = self.blocks[ip].as_ref().unwrap();
+            let halts = matches!(block.exit, Exit::Next(next) if next >= len);
+            for (i, op) in block.ops.iter().enumerate() {
+                op(regs, output)?;
+                steps += 1;
+                if steps >= self.max_steps && !(halts && i + 1 == block.ops.len()) {
+                    return Err(VmError::StepLimit(steps));
+                }
+            }
+            ip = match block.exit {
+                Exit::Next(next) => next,
+                Exit::Jnz { target, next } => {
+                    steps += 1;
+                    let to = if regs.a != 0 { target } else { next };
+                    if steps >= self.max_steps && to < len {
+                        return Err(VmError::StepLimit(steps));
+                    }
+                    to
+                }
+            };
+            if !keep_going(output) {
+                break;
+            }
+        }
+        Ok(ip)
+    }
+
+    // As VM::run, from the top with the VM's registers, leaving the VM where the
+    // program ended
+    pub(crate) fn run(&self, vm: &mut VM) -> Result<Output, VmError> {
+        let mut regs = vm.registers();
+        let res = self.execute(&mut regs, &mut vm.output, |_| true);
+        vm.reg_a = regs.a;
+        vm.reg_b = regs.b;
+        vm.reg_c = regs.c;
+        vm.instruction_pointer = res?;
+        Ok(Output(vm.output.clone()))
+    }
+
+    // As VM::prints, from the top with these registers
+    pub(crate) fn prints(&self, mut regs: Registers, expected: &[usize]) -> bool {
+        let mut output = Vec::with_capacity(expected.len());
+        match self.execute(&mut regs, &mut output, |out| expected.starts_with(out)) {
+            Ok(_) => output == expected,
+            Err(_) => false,
+        }
+    }
+
+    // As VM::repeat_run and VM::par_repeat_run, keeping B and C from the VM
+    pub(crate) fn repeat_run(&self, vm: &VM, lower: u64, upper: u64) -> Option<u64> {
+        (lower..upper).find(|&a| {
+            self.prints(
+                Registers {
+                    a,
+                    ..vm.registers()
+                },
+                &vm.program,
+            )
+        })
+    }
+
+    pub(crate) fn par_repeat_run(&self, vm: &VM, lower: u64, upper: u64) -> Option<u64> {
+        (lower..upper).into_par_iter().find_first(|&a| {
+            self.prints(
+                Registers {
+                    a,
+                    ..vm.registers()
+                },
+                &vm.program,
+            )
+        })
+    }
+}
+
+impl VM {
+    // par_repeat_run through the compiled program, or the interpreter if the
+    // program won't compile
+    pub(crate) fn repeat_run_compiled(&self, lower: u64, upper: u64) -> Option<u64> {
+        match self.compile() {
+            Ok(compiled) => compiled.par_repeat_run(self, lower, upper),
+            Err(_) => self.par_repeat_run(lower, upper),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::debugger::{Debugger, StopReason};
+use crate::vm::VM;
+use eframe::egui::{self, Color32, RichText};
+
+pub fn egui_main(file: &str) -> Result<(), eframe::Error> {
+    env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([900.0, 600.0]),
+        ..Default::default()
+    };
+    let file = file.to_string();
+    eframe::run_native(
+        "AoC2024 - Day 17",
+        options,
+        Box::new(|_cc| Ok(Box::new(VmApp::new(file)))),
+    )
+}
+
+// The model, a debugger on the VM loaded from file
+struct VmApp {
+    file: String,
+    debugger: Debugger,
+    // disassembly, one line per instruction at even addresses
+    listing: Vec<String>,
+    running: bool,
+    status: String,
+    // ms between steps while running
+    delay: f64,
+    delta: f64,
+}
+
+impl VmApp {
+    fn new(file: String) -> Self {
+        let vm = VM::new(&file);
+        let listing = vm.disassemble().lines().skip(3).map(String::from).collect();
+        Self {
+            file,
+            debugger: Debugger::new(vm),
+            listing,
+            running: false,
+            status: "Ready".to_string(),
+            delay: 100.0,
+            delta: 0.0,
+        }
+    }
+
+    // Reread the file, so edits to it show up, and start over
+    fn reset(&mut self) {
+        let vm = VM::new(&self.file);
+        self.listing = vm.disassemble().lines().skip(3).map(String::from).collect();
+        self.debugger.reset(vm);
+        self.running = false;
+        self.status = "Ready".to_string();
+    }
+
+    fn step(&mut self) {
+        match self.debugger.step() {
+            Ok(Some(_)) => {
+                self.status = format!("Step {}", self.debugger.trace.len());
+                if self.running {
+                    if let Some(reason) = self.debugger.breakpoint_hit() {
+                        self.stop(reason);
+                    }
+                }
+            }
+            Ok(None) => self.stop(StopReason::Halted),
+            Err(e) => self.stop(StopReason::Error(e)),
+        }
+    }
+
+    fn stop(&mut self, reason: StopReason) {
+        self.running = false;
+        self.status = match reason {
+            StopReason::Halted => "Halted".to_string(),
+            StopReason::Breakpoint(ip) => format!("Breakpoint at {}", ip),
+            StopReason::OutputCount(n) => format!("Output reached {}", n),
+            StopReason::Error(e) => e.to_string(),
+        };
+    }
+
+    fn listing_panel(&mut self, ui: &mut egui::Ui) {
+        let ip = self.debugger.vm.instruction_pointer;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (i, line) in self.listing.iter().enumerate() {
+                let address = i * 2;
+                ui.horizontal(|ui| {
+                    let mut breakpoint = self.debugger.breakpoints.contains(&address);
+                    if ui.checkbox(&mut breakpoint, "").changed() {
+                        self.debugger.toggle_breakpoint(address);
+                    }
+                    let text = RichText::new(format!("{:>3}  {}", address, line)).monospace();
+                    if address == ip {
+                        ui.label(
+                            text.strong()
+                                .color(Color32::BLACK)
+                                .background_color(Color32::YELLOW),
+                        );
+                    } else {
+                        ui.label(text);
+                    }
+                });
+            }
+        });
+    }
+
+    fn registers_panel(&self, ui: &mut egui::Ui) {
+        let regs = self.debugger.vm.registers();
+        egui::Grid::new("registers").striped(true).show(ui, |ui| {
+            for heading in ["", "binary", "octal", "decimal"] {
+                ui.label(RichText::new(heading).strong());
+            }
+            ui.end_row();
+            for (name, value) in [("A", regs.a), ("B", regs.b), ("C", regs.c)] {
+                ui.label(RichText::new(name).strong());
+                ui.label(RichText::new(format!("{:b}", value)).monospace());
+                ui.label(RichText::new(format!("{:o}", value)).monospace());
+                ui.label(RichText::new(value.to_string()).monospace());
+                ui.end_row();
+            }
+        });
+        ui.label(format!("IP: {}", self.debugger.vm.instruction_pointer));
+    }
+
+    // Output so far, green where it matches the program (the quine so far)
+    fn output_tape(&self, ui: &mut egui::Ui) {
+        let vm = &self.debugger.vm;
+        ui.horizontal_wrapped(|ui| {
+            for (i, value) in vm.output.iter().enumerate() {
+                let colour = if vm.program.get(i) == Some(value) {
+                    Color32::from_rgb(0, 150, 0)
+                } else {
+                    Color32::from_rgb(150, 150, 150)
+                };
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.label(RichText::new(value.to_string()).monospace().color(colour));
+                });
+            }
+        });
+    }
+}
+
+impl eframe::App for VmApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("Top").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Reset").clicked() {
+                    self.reset();
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(!self.running, egui::Button::new("Step"))
+                    .clicked()
+                {
+                    self.step();
+                }
+                let button_text = if self.running { "⏸ Pause" } else { "▶ Run" };
+                if ui.button(button_text).clicked() {
+                    self.running = !self.running;
+                    if self.running {
+                        // Off the breakpoint we're sat on before checking for the next
+                        self.step();
+                    } else {
+                        self.status = "Paused".to_string();
+                    }
+                }
+                ui.separator();
+                ui.label("Delay:");
+                ui.add(egui::Slider::new(&mut self.delay, 0.0..=1000.0).suffix(" ms"));
+                ui.separator();
+                ui.label(&self.status);
+            });
+        });
+        egui::SidePanel::left("Listing")
+            .min_width(320.0)
+            .show(ctx, |ui| {
+                ui.heading("Program");
+                self.listing_panel(ui);
+            });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Registers");
+            self.registers_panel(ui);
+            ui.separator();
+            ui.heading("Output");
+            self.output_tape(ui);
+            ui.separator();
+            ui.label(format!("{} steps taken", self.debugger.trace.len()));
+        });
+
+        // Step when enough time has gone by since the last one
+        let delta = ctx.input(|i| i.time);
+        if self.running && delta - self.delta > self.delay / 1000.0 {
+            self.delta = delta;
+            self.step();
+        }
+        if self.running {
+            ctx.request_repaint();
+        }
+    }
+}
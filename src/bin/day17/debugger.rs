@@ -0,0 +1,214 @@
+use crate::disasm::combo_name;
+use crate::vm::{Opcode, OperandKind, Step, VmError, VM};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+// Step through a program one instruction at a time. Breakpoints stop a continue
+// at an instruction pointer or once the output reaches a given length, and every
+// step taken is kept in a trace that can be dumped as a table.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    Halted,
+    Breakpoint(usize),
+    OutputCount(usize),
+    Error(VmError),
+}
+
+pub(crate) struct Debugger {
+    pub(crate) vm: VM,
+    pub(crate) breakpoints: BTreeSet<usize>,
+    pub(crate) output_breakpoint: Option<usize>,
+    pub(crate) trace: Vec<Step>,
+}
+
+impl Debugger {
+    pub(crate) fn new(vm: VM) -> Self {
+        Self {
+            vm,
+            breakpoints: BTreeSet::new(),
+            output_breakpoint: None,
+            trace: Vec::new(),
+        }
+    }
+
+    // Returns whether the breakpoint is now set
+    pub(crate) fn toggle_breakpoint(&mut self, ip: usize) -> bool {
+        if !self.breakpoints.remove(&ip) {
+            self.breakpoints.insert(ip);
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn break_on_output(&mut self, count: Option<usize>) {
+        self.output_breakpoint = count;
+    }
+
+    pub(crate) fn step(&mut self) -> Result<Option<&Step>, VmError> {
+        let Some(step) = self.vm.step()? else {
+            return Ok(None);
+        };
+        self.trace.push(step);
+        Ok(self.trace.last())
+    }
+
+    // Run until the program halts, fails or a breakpoint is hit. Always takes at
+    // least one step, so continuing from a breakpoint moves off it. Gives up after
+    // the VM's max_steps like run does.
+    pub(crate) fn continue_run(&mut self) -> StopReason {
+        let mut steps = 0;
+        loop {
+            match self.step() {
+                Ok(Some(_)) => steps += 1,
+                Ok(None) => return StopReason::Halted,
+                Err(e) => return StopReason::Error(e),
+            }
+            if steps >= self.vm.max_steps && self.vm.instruction_pointer < self.vm.program.len() {
+                return StopReason::Error(VmError::StepLimit(steps));
+            }
+            if let Some(reason) = self.breakpoint_hit() {
+                return reason;
+            }
+        }
+    }
+
+    // Whether the step just taken should stop a run: it printed the output count
+    // asked for, or the next instruction has a breakpoint
+    pub(crate) fn breakpoint_hit(&self) -> Option<StopReason> {
+        let outputs = self.vm.output.len();
+        if self.output_breakpoint == Some(outputs)
+            && self.trace.last().is_some_and(|s| s.output.is_some())
+        {
+            return Some(StopReason::OutputCount(outputs));
+        }
+        let ip = self.vm.instruction_pointer;
+        if self.breakpoints.contains(&ip) {
+            return Some(StopReason::Breakpoint(ip));
+        }
+        None
+    }
+
+    // Back to the start with a fresh VM, keeping the breakpoints
+    pub(crate) fn reset(&mut self, vm: VM) {
+        self.vm = vm;
+        self.trace.clear();
+    }
+
+    pub(crate) fn trace_table(&self) -> String {
+        let mut res = format!(
+            "{:>5} {:>3}  {:<9} {:>24} {:>24} {:>24}  {}\n",
+            "step", "ip", "instr", "A (oct/dec)", "B (oct/dec)", "C (oct/dec)", "out"
+        );
+        for (i, step) in self.trace.iter().enumerate() {
+            res += &format!(
+                "{:>5} {:>3}  {:<9} {:>24} {:>24} {:>24}  {}\n",
+                i + 1,
+                step.ip,
+                instruction_text(step.instruction, step.operand),
+                register_text(step.after.a),
+                register_text(step.after.b),
+                register_text(step.after.c),
+                step.output.map_or(String::new(), |o| o.to_string()),
+            );
+        }
+        res
+    }
+
+    pub(crate) fn registers_text(&self) -> String {
+        let regs = self.vm.registers();
+        format!(
+            "ip {}  A {}  B {}  C {}  output [{}]",
+            self.vm.instruction_pointer,
+            register_text(regs.a),
+            register_text(regs.b),
+            register_text(regs.c),
+            self.vm
+                .output
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+fn instruction_text(instruction: usize, operand: usize) -> String {
+    match Opcode::from_code(instruction) {
+        Some(opcode) if opcode.operand_kind() == OperandKind::Combo => {
+            format!("{} {}", opcode.mnemonic(), combo_name(operand))
+        }
+        Some(opcode) => format!("{} {}", opcode.mnemonic(), operand),
+        None => format!("? {} {}", instruction, operand),
+    }
+}
+
+fn register_text(value: u64) -> String {
+    format!("0o{:o}/{}", value, value)
+}
+
+fn describe_step(step: &Step) -> String {
+    let mut res = format!(
+        "{:>3}  {}",
+        step.ip,
+        instruction_text(step.instruction, step.operand)
+    );
+    for (register, before, after) in step.deltas() {
+        res += &format!("  {}: {} -> {}", register, before, after);
+    }
+    if let Some(output) = step.output {
+        res += &format!("  out {}", output);
+    }
+    res
+}
+
+const HELP: &str = "s or enter: step, c: continue, b <ip>: toggle breakpoint, \
+o <n>: break when output reaches n (o alone clears), r: registers, t: trace, q: quit";
+
+// Interactive stepping on stdin/stdout
+pub(crate) fn run_interactive(vm: VM) {
+    let mut debugger = Debugger::new(vm);
+    println!("{}", HELP);
+    println!("{}", debugger.registers_text());
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] | ["s"] => match debugger.step() {
+                Ok(Some(step)) => println!("{}", describe_step(step)),
+                Ok(None) => println!("halted"),
+                Err(e) => println!("{}", e),
+            },
+            ["c"] => {
+                let reason = debugger.continue_run();
+                match reason {
+                    StopReason::Halted => println!("halted"),
+                    StopReason::Breakpoint(ip) => println!("breakpoint at {}", ip),
+                    StopReason::OutputCount(n) => println!("output reached {}", n),
+                    StopReason::Error(e) => println!("{}", e),
+                }
+                println!("{}", debugger.registers_text());
+            }
+            ["b", ip] => match ip.parse() {
+                Ok(ip) if debugger.toggle_breakpoint(ip) => println!("breakpoint set at {}", ip),
+                Ok(ip) => println!("breakpoint cleared at {}", ip),
+                Err(_) => println!("bad address {:?}", ip),
+            },
+            ["o"] => debugger.break_on_output(None),
+            ["o", n] => match n.parse() {
+                Ok(n) => debugger.break_on_output(Some(n)),
+                Err(_) => println!("bad count {:?}", n),
+            },
+            ["r"] => println!("{}", debugger.registers_text()),
+            ["t"] => print!("{}", debugger.trace_table()),
+            ["q"] => break,
+            _ => println!("{}", HELP),
+        }
+    }
+}
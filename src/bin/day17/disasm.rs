@@ -0,0 +1,106 @@
+use crate::vm::{Opcode, OperandKind, VM};
+use std::collections::BTreeSet;
+
+// Listing of the program, one line per instruction. Jump targets get a label
+// (L<address>), combo operands show the register they read, and each line has a
+// comment saying what it does.
+//
+//     L0:  bst A      ; B = A % 8
+//          bxl 1      ; B = B ^ 1
+//          ...
+//          jnz L0     ; if A != 0 goto L0
+//
+// Anything that can't be an instruction (unknown opcode, operand over 7, or a lone
+// value at the end) comes out as `.raw` with the values as is. The registers go
+// on top as `.register` lines, so the listing assembles back to the same file.
+
+impl VM {
+    pub(crate) fn disassemble(&self) -> String {
+        let labels = self.jump_targets();
+        let mut res = format!(
+            ".register A {}\n.register B {}\n.register C {}\n",
+            self.reg_a, self.reg_b, self.reg_c
+        );
+        let mut ip = 0;
+        while ip < self.program.len() {
+            let prefix = if labels.contains(&ip) {
+                format!("{}:", label(ip))
+            } else {
+                String::new()
+            };
+            let opcode = Opcode::from_code(self.program[ip]);
+            let (text, comment) = match (opcode, self.program.get(ip + 1)) {
+                (Some(_), Some(operand)) if *operand > 7 => (
+                    format!(".raw {} {}", self.program[ip], operand),
+                    "operand out of range".to_string(),
+                ),
+                (Some(opcode), Some(operand)) => {
+                    let operand_text = operand_text(opcode, *operand, &labels);
+                    (
+                        format!("{} {}", opcode.mnemonic(), operand_text),
+                        describe(opcode, *operand, &operand_text),
+                    )
+                }
+                (None, Some(operand)) => (
+                    format!(".raw {} {}", self.program[ip], operand),
+                    "unknown opcode".to_string(),
+                ),
+                (_, None) => (
+                    format!(".raw {}", self.program[ip]),
+                    "no operand".to_string(),
+                ),
+            };
+            res += &format!("{:<5}{:<11}; {}\n", prefix, text, comment);
+            ip += 2;
+        }
+        res
+    }
+
+    // Even addresses inside the program that a jnz can land on
+    pub(crate) fn jump_targets(&self) -> BTreeSet<usize> {
+        self.program
+            .chunks(2)
+            .filter(|pair| pair.len() == 2 && pair[0] == Opcode::Jnz.code())
+            .map(|pair| pair[1])
+            .filter(|target| target % 2 == 0 && *target <= 7 && *target < self.program.len())
+            .collect()
+    }
+}
+
+pub(crate) fn label(address: usize) -> String {
+    format!("L{}", address)
+}
+
+pub(crate) fn combo_name(operand: usize) -> String {
+    match operand {
+        4 => "A".to_string(),
+        5 => "B".to_string(),
+        6 => "C".to_string(),
+        _ => operand.to_string(),
+    }
+}
+
+fn operand_text(opcode: Opcode, operand: usize, labels: &BTreeSet<usize>) -> String {
+    match opcode.operand_kind() {
+        OperandKind::Combo => combo_name(operand),
+        OperandKind::Jump if labels.contains(&operand) => label(operand),
+        OperandKind::Literal | OperandKind::Jump | OperandKind::Ignored => operand.to_string(),
+    }
+}
+
+// What the instruction does, given its operand as shown
+fn describe(opcode: Opcode, operand: usize, x: &str) -> String {
+    if opcode.operand_kind() == OperandKind::Combo && operand == 7 {
+        return "combo operand 7 is reserved".to_string();
+    }
+    match opcode {
+        Opcode::Adv => format!("A = A >> {}", x),
+        Opcode::Bxl => format!("B = B ^ {}", x),
+        Opcode::Bst => format!("B = {} % 8", x),
+        Opcode::Jnz => format!("if A != 0 goto {}", x),
+        Opcode::Bxc => "B = B ^ C".to_string(),
+        Opcode::Out => format!("out {} % 8", x),
+        Opcode::Bdv => format!("B = A >> {}", x),
+        Opcode::Cdv => format!("C = A >> {}", x),
+    }
+}
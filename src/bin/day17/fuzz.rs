@@ -0,0 +1,327 @@
+use crate::vm::{VmError, VM};
+use std::fs;
+use std::path::Path;
+
+// Differential fuzzing. Random programs and registers go through the VM and through
+// a small evaluator written straight from the puzzle text, which shares no code with
+// it, and the two have to agree on output, final registers and how the run ended.
+// The VM's steps are also checked against a few things that always hold (bst leaves
+// B < 8, out prints 0-7, jnz and out leave the registers alone, adv never grows A).
+// A failing case is shrunk to as small a program and registers as still fail, and
+// can be saved as a regression file in the VM's input format. The regression
+// directory also holds seed_ files, edge cases picked by hand rather than found
+// by fuzzing, which are checked the same way.
+
+// Small enough that looping programs give up quickly
+const FUZZ_MAX_STEPS: usize = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Case {
+    pub(crate) program: Vec<usize>,
+    pub(crate) a: u64,
+    pub(crate) b: u64,
+    pub(crate) c: u64,
+}
+
+impl Case {
+    pub(crate) fn vm(&self) -> VM {
+        let mut vm = VM::parse("");
+        vm.program = self.program.clone();
+        vm.reset(self.a, self.b, self.c);
+        vm.max_steps = FUZZ_MAX_STEPS;
+        vm
+    }
+
+    // Loading back through VM::new gives the same VM apart from max_steps
+    pub(crate) fn save(&self, dir: &Path) -> std::io::Result<String> {
+        fs::create_dir_all(dir)?;
+        let name = format!(
+            "regression_{}_a{}_b{}_c{}.txt",
+            self.program
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join("-"),
+            self.a,
+            self.b,
+            self.c
+        );
+        let path = dir.join(&name);
+        fs::write(&path, self.vm().to_file_format())?;
+        Ok(path.display().to_string())
+    }
+}
+
+// How a run ended, as far as the comparison cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Ending {
+    Halted,
+    // couldn't carry on from the instruction at ip
+    Failed(usize),
+    OutOfSteps,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Outcome {
+    pub(crate) output: Vec<usize>,
+    pub(crate) registers: (u64, u64, u64),
+    pub(crate) ending: Ending,
+}
+
+// The puzzle's description, line by line. A / 2^n is 0 once 2^n is more than any
+// u64.
+pub(crate) fn reference(case: &Case, max_steps: usize) -> Outcome {
+    let p = &case.program;
+    let (mut a, mut b, mut c) = (case.a, case.b, case.c);
+    let mut ip = 0;
+    let mut output = Vec::new();
+    let mut steps = 0;
+    let ending = loop {
+        if ip >= p.len() {
+            break Ending::Halted;
+        }
+        if ip + 1 >= p.len() {
+            break Ending::Failed(ip);
+        }
+        let literal = p[ip + 1];
+        let combo = match literal {
+            0..=3 => Some(literal as u64),
+            4 => Some(a),
+            5 => Some(b),
+            6 => Some(c),
+            _ => None,
+        };
+        let divided = combo.map(|n| if n < 64 { a / (1u64 << n) } else { 0 });
+        match (p[ip], combo, divided) {
+            (0, _, Some(d)) => a = d,
+            (1, _, _) => b ^= literal as u64,
+            (2, Some(n), _) => b = n % 8,
+            (3, _, _) if a != 0 => {
+                ip = literal;
+                steps += 1;
+                if steps >= max_steps && ip < p.len() {
+                    break Ending::OutOfSteps;
+                }
+                continue;
+            }
+            (3, _, _) => {}
+            (4, _, _) => b ^= c,
+            (5, Some(n), _) => output.push((n % 8) as usize),
+            (6, _, Some(d)) => b = d,
+            (7, _, Some(d)) => c = d,
+            _ => break Ending::Failed(ip),
+        }
+        ip += 2;
+        steps += 1;
+        if steps >= max_steps && ip < p.len() {
+            break Ending::OutOfSteps;
+        }
+    };
+    Outcome {
+        output,
+        registers: (a, b, c),
+        ending,
+    }
+}
+
+// Run the VM a step at a time checking the invariants, then compare with the
+// reference. Err says what went wrong.
+pub(crate) fn check(case: &Case) -> Result<(), String> {
+    let mut vm = case.vm();
+    let mut steps = 0;
+    let ending = loop {
+        let step = match vm.step() {
+            Ok(Some(step)) => step,
+            Ok(None) => break Ending::Halted,
+            Err(VmError::StepLimit(_)) => unreachable!(),
+            Err(e) => {
+                let ip = match e {
+                    VmError::MissingOperand { ip }
+                    | VmError::UnknownOpcode { ip, .. }
+                    | VmError::ReservedCombo { ip } => ip,
+                    VmError::StepLimit(_) => unreachable!(),
+                };
+                break Ending::Failed(ip);
+            }
+        };
+        match step.instruction {
+            0 if step.after.a > step.before.a => {
+                return Err(format!("adv grew A at ip {}", step.ip))
+            }
+            2 if step.after.b >= 8 => {
+                return Err(format!("bst left B = {} at ip {}", step.after.b, step.ip))
+            }
+            3 | 5 if step.after != step.before => {
+                return Err(format!(
+                    "registers changed by {} at ip {}",
+                    step.instruction, step.ip
+                ))
+            }
+            5 if step.output.is_none_or(|o| o >= 8) => {
+                return Err(format!("out printed {:?} at ip {}", step.output, step.ip))
+            }
+            _ => {}
+        }
+        steps += 1;
+        if steps >= vm.max_steps && vm.instruction_pointer < vm.program.len() {
+            break Ending::OutOfSteps;
+        }
+    };
+    let got = Outcome {
+        output: vm.output.clone(),
+        registers: (vm.reg_a, vm.reg_b, vm.reg_c),
+        ending,
+    };
+    let expected = reference(case, FUZZ_MAX_STEPS);
+    if got != expected {
+        return Err(format!("VM gave {:?}, reference {:?}", got, expected));
+    }
+
+    // run() has to end the same way stepping did
+    let mut vm = case.vm();
+    let run = vm.run();
+    let same = match (&run, ending) {
+        (Ok(_), Ending::Halted) => true,
+        (Err(VmError::StepLimit(_)), Ending::OutOfSteps) => true,
+        (Err(VmError::StepLimit(_)), _) | (Ok(_), _) => false,
+        (Err(_), Ending::Failed(_)) => true,
+        (Err(_), _) => false,
+    };
+    if !same || vm.output != expected.output {
+        return Err(format!("run gave {:?}, stepping {:?}", run, ending));
+    }
+    Ok(())
+}
+
+// xorshift, so a seed always gives the same cases
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // xorshift is stuck at 0
+        Rng(seed.max(1))
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+// Mostly 3-bit values with the odd out of range one, registers of all sizes
+pub(crate) fn random_case(rng: &mut Rng) -> Case {
+    let len = 1 + rng.below(16) as usize;
+    let program = (0..len)
+        .map(|_| match rng.below(20) {
+            0 => 8 + rng.below(8) as usize,
+            _ => rng.below(8) as usize,
+        })
+        .collect();
+    let register = |rng: &mut Rng| match rng.below(4) {
+        0 => rng.below(8),
+        1 => rng.below(128),
+        _ => rng.next_u64() >> rng.below(64),
+    };
+    Case {
+        program,
+        a: register(rng),
+        b: register(rng),
+        c: register(rng),
+    }
+}
+
+// Smaller versions of a case to try, most shrinking first
+fn shrinks(case: &Case) -> Vec<Case> {
+    let mut res = Vec::new();
+    let p = &case.program;
+    // Drop an instruction, then a single value
+    for width in [2, 1] {
+        for i in 0..p.len().saturating_sub(width - 1) {
+            let mut program = p.clone();
+            program.drain(i..i + width);
+            if !program.is_empty() {
+                res.push(Case {
+                    program,
+                    ..case.clone()
+                });
+            }
+        }
+    }
+    // Smaller values
+    for i in 0..p.len() {
+        for smaller in [0, p[i] / 2, p[i].saturating_sub(1)] {
+            if smaller < p[i] {
+                let mut program = p.clone();
+                program[i] = smaller;
+                res.push(Case {
+                    program,
+                    ..case.clone()
+                });
+            }
+        }
+    }
+    let smaller_registers = |r: u64| {
+        let mut candidates = vec![0, r % 8, r % 64, r / 2, r / 8, r.saturating_sub(1)];
+        candidates.retain(|&s| s < r);
+        candidates
+    };
+    for a in smaller_registers(case.a) {
+        res.push(Case { a, ..case.clone() });
+    }
+    for b in smaller_registers(case.b) {
+        res.push(Case { b, ..case.clone() });
+    }
+    for c in smaller_registers(case.c) {
+        res.push(Case { c, ..case.clone() });
+    }
+    res
+}
+
+// Keep taking the first smaller case that still fails until none do
+pub(crate) fn shrink(case: &Case, fails: impl Fn(&Case) -> bool) -> Case {
+    let mut best = case.clone();
+    while let Some(smaller) = shrinks(&best).into_iter().find(|c| fails(c)) {
+        best = smaller;
+    }
+    best
+}
+
+// The first failing case from `count` random ones, shrunk, with what went wrong
+pub(crate) fn fuzz(seed: u64, count: usize) -> Option<(Case, String)> {
+    let mut rng = Rng::new(seed);
+    let failing = (0..count)
+        .map(|_| random_case(&mut rng))
+        .find(|case| check(case).is_err())?;
+    let shrunk = shrink(&failing, |c| check(c).is_err());
+    let message = check(&shrunk).unwrap_err();
+    Some((shrunk, message))
+}
+
+// Every file in a regression directory, seeds and regressions, read back as a case
+pub(crate) fn regressions(dir: &Path) -> Vec<(String, Case)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut res: Vec<(String, Case)> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .map(|path| {
+            let vm = VM::new(path.to_str().unwrap());
+            let case = Case {
+                program: vm.program,
+                a: vm.reg_a,
+                b: vm.reg_b,
+                c: vm.reg_c,
+            };
+            (path.display().to_string(), case)
+        })
+        .collect();
+    res.sort_by(|x, y| x.0.cmp(&y.0));
+    res
+}
@@ -1,21 +1,114 @@
+mod asm;
+mod compile;
+mod day17_in_egui;
+mod debugger;
+mod disasm;
+mod fuzz;
+mod quine;
+mod register;
 mod vm;
 
-fn main() {}
+use num::BigUint;
+use quine::QuineError;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+use vm::VM;
+
+// cargo run --bin day17 -- [run|disasm|asm|solve|brute|debug|gui|fuzz] <file>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (mode, file) = match args.len() {
+        2 => ("run", args[1].as_str()),
+        3 => (args[1].as_str(), args[2].as_str()),
+        _ => {
+            eprintln!("Usage: day17 [run|disasm|asm|solve|brute|debug|gui|fuzz] <file>");
+            return;
+        }
+    };
+    match mode {
+        "run" => match VM::new(file).run() {
+            Ok(out) => println!("{}", out),
+            Err(e) => eprintln!("{}: {}", file, e),
+        },
+        "disasm" => print!("{}", VM::new(file).disassemble()),
+        "asm" => {
+            let source = fs::read_to_string(file).expect("Can't read the file");
+            match asm::assemble(&source) {
+                Ok(out) => print!("{}", out),
+                Err(e) => eprintln!("{}: {}", file, e),
+            }
+        }
+        "solve" => match VM::new(file).reconstruct() {
+            Ok(a) => println!("{} (0o{:o})", a, a),
+            // Too many digits for u64, so go wide
+            Err(QuineError::TooLong { .. }) => match VM::<BigUint>::from_file(file).reconstruct() {
+                Ok(a) => println!("{} (0o{:o})", a, a),
+                Err(e) => eprintln!("{}: {}", file, e),
+            },
+            Err(e) => eprintln!("{}: {}", file, e),
+        },
+        // Smallest quine A below 2^32 the slow way, for checking solve
+        "brute" => match VM::new(file).repeat_run_compiled(0, 1 << 32) {
+            Some(a) => println!("{} (0o{:o})", a, a),
+            None => eprintln!("{}: no A below 2^32 prints the program", file),
+        },
+        "debug" => debugger::run_interactive(VM::new(file)),
+        "gui" => day17_in_egui::egui_main(file).unwrap(),
+        // Here file is the regression directory, failures get saved there
+        "fuzz" => run_fuzz(Path::new(file)),
+        _ => eprintln!(
+            "Unknown mode {:?}, expected run, disasm, asm, solve, brute, debug, gui or fuzz",
+            mode
+        ),
+    }
+}
+
+fn run_fuzz(dir: &Path) {
+    for (name, case) in fuzz::regressions(dir) {
+        if let Err(e) = fuzz::check(&case) {
+            println!("{} still fails: {}", name, e);
+        }
+    }
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    println!("Seed {}", seed);
+    match fuzz::fuzz(seed, 1_000_000) {
+        Some((case, message)) => {
+            println!("{}", message);
+            match case.save(dir) {
+                Ok(path) => println!("Saved to {}", path),
+                Err(e) => eprintln!("Can't save to {}: {}", dir.display(), e),
+            }
+        }
+        None => println!("No differences"),
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::vm::VM;
+    use crate::asm::{assemble, assemble_vm};
+    use crate::compile::CompileError;
+    use crate::debugger::{Debugger, StopReason};
+    use crate::fuzz::{self, Case, Ending, Rng};
+    use crate::quine::QuineError;
+    use crate::vm::{VmError, VM};
+    use num::BigUint;
+    use std::path::Path;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[test]
     fn test_part_one_test() {
         let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
-        assert_eq!(vm.run(), "4,6,3,5,6,3,5,2,1,0");
+        assert_eq!(vm.run().unwrap().to_string(), "4,6,3,5,6,3,5,2,1,0");
     }
 
     #[test]
     fn test_part_one_data() {
         let mut vm = VM::new("src/bin/day17/data/day17_data.txt");
-        assert_eq!(vm.run(), "6,5,7,4,5,7,3,1,0");
+        assert_eq!(vm.run().unwrap().to_string(), "6,5,7,4,5,7,3,1,0");
     }
 
     #[test]
@@ -24,13 +117,541 @@ mod tests {
         assert_eq!(vm.repeat_run(0, 2000000), Some(117440));
     }
 
+    #[test]
+    fn test_part_two_test_compiled() {
+        let vm = VM::new("src/bin/day17/data/day17_test_part2.txt");
+        assert_eq!(vm.repeat_run_compiled(0, 2000000), Some(117440));
+        let compiled = vm.compile().unwrap();
+        assert_eq!(compiled.repeat_run(&vm, 0, 2000000), Some(117440));
+        assert_eq!(compiled.par_repeat_run(&vm, 0, 117440), None);
+    }
+
+    #[test]
+    fn test_part_two_test_parallel() {
+        let vm = VM::new("src/bin/day17/data/day17_test_part2.txt");
+        assert_eq!(vm.par_repeat_run(0, 2000000), Some(117440));
+        assert_eq!(vm.par_repeat_run(0, 117440), None);
+    }
+
+    #[test]
+    fn test_part_two_test_reconstruct() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test_part2.txt");
+        assert_eq!(vm.reconstruct(), Ok(117440));
+    }
+
     #[test]
     fn test_part_two_data() {
         let mut vm = VM::new("src/bin/day17/data/day17_data.txt");
-        let fred: u64 = 0o3553461305751420;
-        let bob = fred - 0;
-        let alice = fred + 10;
-        
-        assert_eq!(vm.repeat_run(bob, alice), Some(117440));
+        let a = vm.reconstruct().unwrap();
+        println!("A = {} (0o{:o})", a, a);
+        // Check it really is a quine, and nothing just below it is
+        assert_eq!(vm.repeat_run(a.saturating_sub(1000), a + 1), Some(a));
+    }
+
+    #[test]
+    fn test_reconstruct_typical_program() {
+        // bst A, bxl 1, cdv B, bxl 5, bxc, adv 3, out B, jnz 0. B and C come from A
+        // each time round, so this is the usual puzzle shape.
+        let mut vm = VM::parse("Program: 2,4,1,1,7,5,1,5,4,0,0,3,5,5,3,0");
+        let a = vm.reconstruct().unwrap();
+        assert_eq!(vm.repeat_run(a.saturating_sub(1000), a + 1), Some(a));
+    }
+
+    #[test]
+    fn test_reconstruct_wrong_shape() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let err = vm.reconstruct().unwrap_err();
+        println!("{}", err);
+        assert_eq!(err, QuineError::NotShiftBy3("adv 1".to_string()));
+
+        for (program, expected) in [
+            (
+                "0,3,5,4",
+                QuineError::NotOneLoop("out 4 at the end".to_string()),
+            ),
+            (
+                "0,3,3,0,5,4,3,0",
+                QuineError::NotOneLoop("2 jnz instructions".to_string()),
+            ),
+            (
+                "0,3,5,4,5,4,3,0",
+                QuineError::NotOneOutput("2 out instructions".to_string()),
+            ),
+            (
+                "0,3,0,3,5,4,3,0",
+                QuineError::NotShiftBy3("adv 3, adv 3".to_string()),
+            ),
+        ] {
+            let mut vm = VM::parse(&format!("Program: {}", program));
+            assert_eq!(vm.reconstruct(), Err(expected));
+        }
+
+        // Outputs a constant, so can never print itself
+        let mut vm = VM::parse("Program: 0,3,5,1,3,0");
+        assert_eq!(vm.reconstruct(), Err(QuineError::NoSolution));
+    }
+
+    #[test]
+    fn test_disassemble_test() {
+        let vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let listing = vm.disassemble();
+        print!("{}", listing);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], ".register A 729");
+        assert!(lines[3].starts_with("L0:  adv 1"));
+        assert!(lines[4].starts_with("     out A"));
+        assert!(lines[5].starts_with("     jnz L0"));
+    }
+
+    #[test]
+    fn test_disassemble_operands() {
+        // every opcode, combo 7, a jump into the middle of an instruction, an unknown
+        // opcode and a lone value on the end
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        vm.program = vec![0, 4, 1, 7, 2, 5, 3, 3, 4, 2, 5, 6, 6, 7, 7, 0, 9, 1, 3];
+        let lines: Vec<String> = vm
+            .disassemble()
+            .lines()
+            .skip(3)
+            .map(|l| l.to_string())
+            .collect();
+        let code: Vec<&str> = lines
+            .iter()
+            .map(|l| l.split(';').next().unwrap().trim())
+            .collect();
+        assert_eq!(
+            code,
+            vec![
+                "adv A", "bxl 7", "bst B", "jnz 3", "bxc 2", "out C", "bdv 7", "cdv 0", ".raw 9 1",
+                ".raw 3"
+            ]
+        );
+        assert!(lines[6].ends_with("reserved"));
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            .register A 2024
+            ; part two example
+            start: adv 3
+                   out A      ; print it
+                   jnz start
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            "Register A: 2024\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0\n"
+        );
+        let mut vm = assemble_vm(source).unwrap();
+        assert_eq!(vm.run().unwrap().to_string(), "5,7,3,0");
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let err = |source: &str| assemble(source).unwrap_err();
+        assert_eq!(err("adv 3\nfoo 1").line, 2);
+        assert_eq!(err("adv 3\n\njnz nowhere").line, 3);
+        assert_eq!(err("bxl 8").line, 1);
+        assert_eq!(err("; comment\nout D").line, 2);
+        assert_eq!(err("x: adv 1\nx: adv 2").line, 2);
+        assert_eq!(err("adv").line, 1);
+        assert_eq!(err(".register D 1").line, 1);
+        println!("{}", err("adv 3\nfoo 1"));
+    }
+
+    #[test]
+    fn test_disassemble_assemble_round_trip() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let programs = vec![
+            vec![0, 1, 5, 4, 3, 0],
+            vec![0, 4, 1, 7, 2, 5, 3, 3, 4, 2, 5, 6, 6, 7, 7, 0, 9, 1, 3],
+            vec![2, 4, 1, 1, 7, 5, 1, 5, 4, 0, 0, 3, 5, 5, 3, 0],
+            vec![1, 12, 3, 10, 5],
+            vec![],
+        ];
+        for program in programs {
+            vm.program = program;
+            vm.reg_b = 17;
+            let listing = vm.disassemble();
+            let file = assemble(&listing).unwrap();
+            assert_eq!(file, vm.to_file_format(), "{}", listing);
+            let back = assemble_vm(&listing).unwrap();
+            assert_eq!(back.disassemble(), listing);
+            // and the file reads back as the same machine
+            let parsed = VM::parse(&file);
+            assert_eq!(parsed.program, vm.program);
+            assert_eq!(parsed.reg_b, 17);
+            assert_eq!(parsed.to_file_format(), file);
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let first = vm.step().unwrap().unwrap();
+        // adv 1 on A = 729
+        assert_eq!((first.ip, first.instruction, first.operand), (0, 0, 1));
+        assert_eq!(first.deltas(), vec![('A', 729, 364)]);
+        assert_eq!(first.output, None);
+        let second = vm.step().unwrap().unwrap();
+        assert_eq!((second.ip, second.output), (2, Some(4)));
+        assert!(second.deltas().is_empty());
+        while vm.step().unwrap().is_some() {}
+        assert_eq!(vm.step().unwrap(), None);
+        assert_eq!(vm.output, vec![4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_debugger_breakpoints() {
+        let mut debugger = Debugger::new(VM::new("src/bin/day17/data/day17_test.txt"));
+        assert!(debugger.toggle_breakpoint(4));
+        assert_eq!(debugger.continue_run(), StopReason::Breakpoint(4));
+        assert_eq!(debugger.trace.len(), 2);
+        // Continuing moves off the breakpoint and round the loop back to it
+        assert_eq!(debugger.continue_run(), StopReason::Breakpoint(4));
+        assert_eq!(debugger.trace.len(), 5);
+        assert!(!debugger.toggle_breakpoint(4));
+
+        debugger.break_on_output(Some(4));
+        assert_eq!(debugger.continue_run(), StopReason::OutputCount(4));
+        assert_eq!(debugger.vm.output, vec![4, 6, 3, 5]);
+        debugger.break_on_output(None);
+        assert_eq!(debugger.continue_run(), StopReason::Halted);
+        assert_eq!(debugger.continue_run(), StopReason::Halted);
+        assert_eq!(debugger.trace.len(), 30);
+    }
+
+    #[test]
+    fn test_debugger_reset() {
+        let mut debugger = Debugger::new(VM::new("src/bin/day17/data/day17_test.txt"));
+        debugger.toggle_breakpoint(4);
+        debugger.step().unwrap();
+        assert_eq!(debugger.breakpoint_hit(), None);
+        debugger.step().unwrap();
+        assert_eq!(debugger.breakpoint_hit(), Some(StopReason::Breakpoint(4)));
+        debugger.reset(VM::new("src/bin/day17/data/day17_test.txt"));
+        assert!(debugger.trace.is_empty());
+        assert_eq!(debugger.vm.instruction_pointer, 0);
+        assert_eq!(debugger.continue_run(), StopReason::Breakpoint(4));
+    }
+
+    #[test]
+    fn test_trace_table() {
+        let mut debugger = Debugger::new(VM::new("src/bin/day17/data/day17_test.txt"));
+        debugger.step().unwrap();
+        debugger.step().unwrap();
+        let table = debugger.trace_table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains("adv 1"));
+        assert!(lines[1].contains("0o554/364"));
+        assert!(lines[2].contains("out A"));
+        assert!(lines[2].ends_with(" 4"));
+    }
+
+    #[test]
+    fn test_vm_errors() {
+        let run = |contents: &str| VM::parse(contents).run();
+        // out A with nothing after it
+        assert_eq!(
+            run("Register A: 1\n\nProgram: 5,4,5"),
+            Err(VmError::MissingOperand { ip: 2 })
+        );
+        // jump onto the last cell
+        assert_eq!(
+            run("Register A: 1\n\nProgram: 3,3,5,4"),
+            Err(VmError::MissingOperand { ip: 3 })
+        );
+        assert_eq!(
+            run("Program: 5,4,9,0"),
+            Err(VmError::UnknownOpcode { ip: 2, opcode: 9 })
+        );
+        assert_eq!(
+            run("Program: 0,4,2,7"),
+            Err(VmError::ReservedCombo { ip: 2 })
+        );
+        // bxc ignores its operand, so 7 is fine there
+        assert!(run("Program: 4,7").is_ok());
+        // A shifted right out of the register is 0, not an error
+        let mut vm = VM::parse("Register A: 64\n\nProgram: 0,4");
+        assert!(vm.run().is_ok());
+        assert_eq!(vm.reg_a, 0);
+        assert_eq!(
+            run("Register A: 1\n\nProgram: 3,0"),
+            Err(VmError::StepLimit(crate::vm::DEFAULT_MAX_STEPS))
+        );
+
+        // Output so far is kept on an error
+        let mut vm = VM::parse("Register A: 5\n\nProgram: 5,4,5,7");
+        assert_eq!(vm.run(), Err(VmError::ReservedCombo { ip: 2 }));
+        assert_eq!(vm.output, vec![5]);
+    }
+
+    #[test]
+    fn test_step_budget() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        // 30 steps to run to the end
+        vm.max_steps = 29;
+        assert_eq!(vm.run(), Err(VmError::StepLimit(29)));
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        vm.max_steps = 30;
+        assert!(vm.run().is_ok());
+
+        let mut debugger = Debugger::new(VM::parse("Register A: 1\n\nProgram: 3,0"));
+        debugger.vm.max_steps = 100;
+        assert_eq!(
+            debugger.continue_run(),
+            StopReason::Error(VmError::StepLimit(100))
+        );
+    }
+
+    #[test]
+    fn test_outputs() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let first: Vec<usize> = vm.outputs().take(3).map(|v| v.unwrap()).collect();
+        assert_eq!(first, vec![4, 6, 3]);
+        // Stopped just after the third out, and carries on from there
+        assert_eq!(vm.output, vec![4, 6, 3]);
+        assert_eq!(vm.instruction_pointer, 4);
+        let rest: Vec<usize> = vm.outputs().map(|v| v.unwrap()).collect();
+        assert_eq!(rest, vec![5, 6, 3, 5, 2, 1, 0]);
+
+        // An error comes out once, then nothing
+        let mut vm = VM::parse("Register A: 5\n\nProgram: 5,4,5,7");
+        let values: Vec<_> = vm.outputs().collect();
+        assert_eq!(values, vec![Ok(5), Err(VmError::ReservedCombo { ip: 2 })]);
+    }
+
+    #[test]
+    fn test_prints_stops_early() {
+        let mut vm = VM::new("src/bin/day17/data/day17_test_part2.txt");
+        // A = 1 prints 0, which matches, then halts too soon
+        vm.reset(1, 0, 0);
+        assert!(!vm.prints_itself());
+        vm.reset(117440, 0, 0);
+        assert!(vm.prints_itself());
+        // Would print 7 values, but the first is already wrong
+        vm.reset(0o3456701, 0, 0);
+        assert!(!vm.prints(&[9, 9, 9, 9, 9, 9, 9]));
+        assert_eq!(vm.output.len(), 1);
+    }
+
+    #[test]
+    fn test_compiled_matches_interpreter() {
+        let mut rng = Rng::new(0x2024_1217);
+        let mut compiled_count = 0;
+        for _ in 0..5000 {
+            let len = 1 + (rng.next_u64() % 16) as usize;
+            let program: Vec<usize> = (0..len).map(|_| (rng.next_u64() % 8) as usize).collect();
+            let mut vm = VM::parse(&format!(
+                "Register A: {}\nRegister B: {}\nRegister C: {}\n\nProgram: {}",
+                rng.next_u64() >> (rng.next_u64() % 64),
+                // past 64 too, for shifts out of the register
+                rng.next_u64() % 80,
+                rng.next_u64() % 80,
+                program
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ));
+            vm.max_steps = 500;
+            let Ok(compiled) = vm.compile() else {
+                continue;
+            };
+            compiled_count += 1;
+            let mut compiled_vm = vm.clone();
+            let expected = vm.run();
+            let got = compiled.run(&mut compiled_vm);
+            assert_eq!(got, expected, "program {:?}", program);
+            assert_eq!(compiled_vm.output, vm.output, "program {:?}", program);
+            assert_eq!(
+                compiled_vm.registers(),
+                vm.registers(),
+                "program {:?}",
+                program
+            );
+            if expected.is_ok() {
+                assert_eq!(compiled_vm.instruction_pointer, vm.instruction_pointer);
+            }
+        }
+        // Most programs have no odd jumps
+        assert!(compiled_count > 4000);
+    }
+
+    #[test]
+    fn test_compile_odd_jump() {
+        let vm = VM::parse("Program: 0,1,3,1");
+        assert_eq!(
+            vm.compile().err(),
+            Some(CompileError::OddJump { ip: 2, target: 1 })
+        );
+        // Falls back to the interpreter
+        assert_eq!(vm.repeat_run_compiled(0, 10), None);
+        // Past the end is just a halt
+        assert!(VM::parse("Program: 0,1,3,6").compile().is_ok());
+    }
+
+    #[test]
+    fn test_wide_registers() {
+        // Same answers whatever the width
+        let mut vm = VM::<u128>::from_file("src/bin/day17/data/day17_test.txt");
+        assert_eq!(vm.run().unwrap().to_string(), "4,6,3,5,6,3,5,2,1,0");
+        let mut vm = VM::<BigUint>::from_file("src/bin/day17/data/day17_test.txt");
+        assert_eq!(vm.run().unwrap().to_string(), "4,6,3,5,6,3,5,2,1,0");
+        let mut vm = VM::<BigUint>::from_file("src/bin/day17/data/day17_test_part2.txt");
+        assert_eq!(vm.reconstruct(), Ok(BigUint::from(117440u32)));
+
+        // A past 64 bits parses and shifts down
+        let source = "Register A: 36893488147419103232\n\nProgram: 0,3,5,4,3,0";
+        let mut vm = VM::<u128>::from_input(source);
+        assert_eq!(vm.reg_a, 1 << 65);
+        vm.run().unwrap();
+        // 2^65 >> 3 a time, printing 0 until it's down to 2^2
+        let mut expected = vec![0; 20];
+        expected.extend([4, 0]);
+        assert_eq!(vm.output, expected);
+        assert_eq!(vm.reg_a, 0);
+    }
+
+    #[test]
+    fn test_wide_shifts() {
+        // A >> B with B = 64: all of A for u64, 4 left for u128 and BigUint
+        let source = "Register A: 73786976294838206464\nRegister B: 64\n\nProgram: 0,5,5,4";
+        let mut vm = VM::parse("Register A: 5\nRegister B: 64\n\nProgram: 0,5,5,4");
+        vm.run().unwrap();
+        assert_eq!(vm.output, vec![0]);
+        let mut vm = VM::<u128>::from_input(source);
+        vm.run().unwrap();
+        assert_eq!(vm.reg_a, 4);
+        assert_eq!(vm.output, vec![4]);
+        let mut vm = VM::<BigUint>::from_input(source);
+        vm.run().unwrap();
+        assert_eq!(vm.reg_a, BigUint::from(4u32));
+
+        // Shifts past every width give 0 whichever register it runs on
+        for (source, expected) in [
+            (
+                "Register A: 12345\nRegister B: 128\n\nProgram: 7,5,5,6",
+                vec![0],
+            ),
+            (
+                "Register A: 12345\nRegister B: 200\n\nProgram: 6,5,5,5",
+                vec![0],
+            ),
+            (
+                "Register A: 12345\nRegister B: 70\n\nProgram: 0,5,5,4",
+                vec![0],
+            ),
+        ] {
+            let mut vm = VM::parse(source);
+            vm.run().unwrap();
+            assert_eq!(vm.output, expected, "{}", source);
+            let mut vm = VM::<u128>::from_input(source);
+            vm.run().unwrap();
+            assert_eq!(vm.output, expected, "{}", source);
+            let mut vm = VM::<BigUint>::from_input(source);
+            vm.run().unwrap();
+            assert_eq!(vm.output, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_long_program() {
+        // A typical program padded out to 24 values with bxl pairs that cancel, more
+        // octal digits than u64 holds
+        let program = "2,4,1,0,7,5,1,5,4,0,1,0,1,0,1,1,1,1,0,3,5,5,3,0";
+        let source = format!("Program: {}", program);
+        assert_eq!(
+            VM::parse(&source).reconstruct(),
+            Err(QuineError::TooLong { len: 24, fits: 21 })
+        );
+        let a = VM::<u128>::from_input(&source).reconstruct().unwrap();
+        assert_eq!(a, 3178160820256387393187);
+        let mut vm = VM::<u128>::from_input(&source);
+        vm.reg_a = a;
+        assert_eq!(vm.run().unwrap().to_string(), program);
+        let wide = VM::<BigUint>::from_input(&source).reconstruct().unwrap();
+        assert_eq!(wide, BigUint::from(a));
+    }
+
+    #[test]
+    fn test_reference_matches_examples() {
+        let vm = VM::new("src/bin/day17/data/day17_test.txt");
+        let case = Case {
+            program: vm.program.clone(),
+            a: vm.reg_a,
+            b: vm.reg_b,
+            c: vm.reg_c,
+        };
+        let outcome = fuzz::reference(&case, 1000);
+        assert_eq!(outcome.output, vec![4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+        assert_eq!(outcome.ending, Ending::Halted);
+        assert!(fuzz::check(&case).is_ok());
+    }
+
+    #[test]
+    fn test_fuzz_vm() {
+        for seed in [1, 17, 2024] {
+            if let Some((case, message)) = fuzz::fuzz(seed, 20_000) {
+                panic!("seed {}: {:?} {}", seed, case, message);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_regressions() {
+        // The hand picked seeds at least
+        let cases = fuzz::regressions(Path::new("src/bin/day17/data/regressions"));
+        assert!(cases.iter().any(|(name, _)| name.contains("seed_")));
+        for (name, case) in cases {
+            assert_eq!(fuzz::check(&case), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        // Pretend anything that prints a 7 is a bug
+        let prints_seven = |case: &Case| {
+            let mut vm = case.vm();
+            _ = vm.run();
+            vm.output.contains(&7)
+        };
+        let mut rng = Rng::new(99);
+        let case = std::iter::repeat_with(|| fuzz::random_case(&mut rng))
+            .find(|case| prints_seven(case) && case.program.len() > 6)
+            .unwrap();
+        let shrunk = fuzz::shrink(&case, prints_seven);
+        assert!(prints_seven(&shrunk));
+        // out with a register holding 7, and nothing else
+        assert_eq!(shrunk.program.len(), 2);
+        assert_eq!(shrunk.program[0], 5);
+        assert_eq!([shrunk.a, shrunk.b, shrunk.c].iter().sum::<u64>(), 7);
+    }
+
+    #[test]
+    fn test_save_regression() {
+        // Somewhere no other run of this test is using
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "day17_regressions_test_{}_{}",
+            std::process::id(),
+            nanos
+        ));
+        let case = Case {
+            program: vec![2, 4, 5, 5, 9],
+            a: 12,
+            b: 3,
+            c: 0,
+        };
+        let path = case.save(&dir).unwrap();
+        assert!(path.ends_with("regression_2-4-5-5-9_a12_b3_c0.txt"));
+        let saved = fuzz::regressions(&dir);
+        assert!(saved.iter().any(|(_, c)| *c == case));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-}
\ No newline at end of file
+}
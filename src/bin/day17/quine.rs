@@ -0,0 +1,155 @@
+use crate::register::Register;
+use crate::vm::{Opcode, VM};
+use std::fmt;
+
+// Part two, the smallest A that makes the program print itself.
+//
+// The puzzle programs are one loop: work out a digit from A, print it, shift A right
+// by 3 and go round again until A is 0. So each output comes from A with the octal
+// digits already printed for knocked off the bottom, and the last output depends
+// only on A's top octal digit. Build A from the top down, trying each octal digit
+// with the VM as the oracle: keep it if the run prints the tail of the program from
+// that point on. Digits tried lowest first, so the first A to match all the way
+// down is the smallest. A needs an octal digit per value in the program, so past 21
+// values use a VM with u128 or BigUint registers.
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum QuineError {
+    // the shape checks, with what was found
+    NotOneLoop(String),
+    NotShiftBy3(String),
+    NotOneOutput(String),
+    // more octal digits (the program length) than fit in A
+    TooLong { len: usize, fits: usize },
+    // fits the shape, but no digit works at some point
+    NoSolution,
+}
+
+impl fmt::Display for QuineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuineError::NotOneLoop(found) => write!(
+                f,
+                "program must be one loop ending in jnz 0 with no other jumps, found {}",
+                found
+            ),
+            QuineError::NotShiftBy3(found) => write!(
+                f,
+                "program must shift A right by 3 once per loop (adv 3), found {}",
+                found
+            ),
+            QuineError::NotOneOutput(found) => {
+                write!(f, "program must output once per loop, found {}", found)
+            }
+            QuineError::TooLong { len, fits } => write!(
+                f,
+                "program is {} long, A would need {} octal digits and only {} fit",
+                len, len, fits
+            ),
+            QuineError::NoSolution => write!(f, "no value of A makes this program print itself"),
+        }
+    }
+}
+
+impl std::error::Error for QuineError {}
+
+impl<R: Register> VM<R> {
+    pub(crate) fn reconstruct(&mut self) -> Result<R, QuineError> {
+        self.check_quine_shape()?;
+        let len = self.program.len();
+        if let Some(fits) = R::OCTAL_DIGITS.filter(|fits| len > *fits) {
+            return Err(QuineError::TooLong { len, fits });
+        }
+        let reg_b = self.reg_b.clone();
+        let reg_c = self.reg_c.clone();
+        let res = self.reconstruct_digit(R::from_u64(0), len, &reg_b, &reg_c);
+        self.reg_b = reg_b;
+        self.reg_c = reg_c;
+        res.ok_or(QuineError::NoSolution)
+    }
+
+    // A has the digits found so far. Try each next digit below them and check that
+    // the program prints itself from `from` on.
+    fn reconstruct_digit(&mut self, a: R, from: usize, reg_b: &R, reg_c: &R) -> Option<R> {
+        if from == 0 {
+            return Some(a);
+        }
+        for digit in 0..8 {
+            let candidate = a.push_octal(digit);
+            // A top digit of 0 is just a shorter A, and would leave A at 0
+            if candidate.is_zero() {
+                continue;
+            }
+            self.reset(candidate.clone(), reg_b.clone(), reg_c.clone());
+            let tail = self.program[from - 1..].to_vec();
+            if self.prints(&tail) {
+                if let Some(res) = self.reconstruct_digit(candidate, from - 1, reg_b, reg_c) {
+                    return Some(res);
+                }
+            }
+        }
+        None
+    }
+
+    // One loop back to the start, one adv 3 and one out in it
+    fn check_quine_shape(&self) -> Result<(), QuineError> {
+        let instructions: Vec<(Option<Opcode>, usize)> = self
+            .program
+            .chunks(2)
+            .map(|pair| (Opcode::from_code(pair[0]), *pair.get(1).unwrap_or(&0)))
+            .collect();
+        let count = |opcode: Opcode| {
+            instructions
+                .iter()
+                .filter(|(o, _)| *o == Some(opcode))
+                .count()
+        };
+
+        if self.program.len() % 2 != 0 || instructions.iter().any(|(o, _)| o.is_none()) {
+            return Err(QuineError::NotOneLoop(
+                "stray values that aren't instructions".to_string(),
+            ));
+        }
+        match instructions.last() {
+            Some((Some(Opcode::Jnz), 0)) if count(Opcode::Jnz) == 1 => {}
+            Some((Some(Opcode::Jnz), 0)) => {
+                return Err(QuineError::NotOneLoop(format!(
+                    "{} jnz instructions",
+                    count(Opcode::Jnz)
+                )))
+            }
+            Some((Some(opcode), operand)) => {
+                return Err(QuineError::NotOneLoop(format!(
+                    "{} {} at the end",
+                    opcode.mnemonic(),
+                    operand
+                )))
+            }
+            _ => return Err(QuineError::NotOneLoop("an empty program".to_string())),
+        }
+        let shifts: Vec<usize> = instructions
+            .iter()
+            .filter(|(o, _)| *o == Some(Opcode::Adv))
+            .map(|(_, operand)| *operand)
+            .collect();
+        if shifts != vec![3] {
+            let found = if shifts.is_empty() {
+                "no adv".to_string()
+            } else {
+                shifts
+                    .iter()
+                    .map(|s| format!("adv {}", s))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            return Err(QuineError::NotShiftBy3(found));
+        }
+        if count(Opcode::Out) != 1 {
+            return Err(QuineError::NotOneOutput(format!(
+                "{} out instructions",
+                count(Opcode::Out)
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use num::{BigUint, ToPrimitive, Zero};
+use std::fmt;
+use std::str::FromStr;
+
+// What the VM needs from a register. u64 does for the puzzle, u128 and BigUint
+// are there for As that don't fit, as longer quines need.
+
+pub(crate) trait Register:
+    Clone + PartialEq + fmt::Debug + fmt::Display + fmt::Octal + fmt::Binary + FromStr + Send + Sync
+{
+    // Octal digits that fit, None for no limit
+    const OCTAL_DIGITS: Option<usize>;
+
+    fn from_u64(value: u64) -> Self;
+
+    // self * 8 + digit
+    fn push_octal(&self, digit: u64) -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    // value % 8, which is all out and bst keep
+    fn low_digit(&self) -> usize;
+
+    fn xor(&self, other: &Self) -> Self;
+
+    // self >> shift, which is self / 2^shift. 0 once the shift is the register's
+    // width or more, whatever the width, as dividing by 2^shift would give.
+    fn shift_right(&self, shift: &Self) -> Self;
+}
+
+macro_rules! fixed_width_register {
+    ($t:ty) => {
+        impl Register for $t {
+            const OCTAL_DIGITS: Option<usize> = Some(<$t>::BITS as usize / 3);
+
+            fn from_u64(value: u64) -> Self {
+                value as $t
+            }
+
+            fn push_octal(&self, digit: u64) -> Self {
+                *self * 8 + digit as $t
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn low_digit(&self) -> usize {
+                (*self % 8) as usize
+            }
+
+            fn xor(&self, other: &Self) -> Self {
+                *self ^ *other
+            }
+
+            fn shift_right(&self, shift: &Self) -> Self {
+                u32::try_from(*shift)
+                    .ok()
+                    .and_then(|s| self.checked_shr(s))
+                    .unwrap_or(0)
+            }
+        }
+    };
+}
+
+fixed_width_register!(u64);
+fixed_width_register!(u128);
+
+impl Register for BigUint {
+    const OCTAL_DIGITS: Option<usize> = None;
+
+    fn from_u64(value: u64) -> Self {
+        BigUint::from(value)
+    }
+
+    fn push_octal(&self, digit: u64) -> Self {
+        self * 8u32 + digit
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn low_digit(&self) -> usize {
+        (self % 8u32).to_usize().unwrap()
+    }
+
+    fn xor(&self, other: &Self) -> Self {
+        self ^ other
+    }
+
+    // Anything shifted by at least its own length is 0
+    fn shift_right(&self, shift: &Self) -> Self {
+        match shift.to_u64() {
+            Some(s) if s < self.bits() => self >> s,
+            _ => BigUint::zero(),
+        }
+    }
+}
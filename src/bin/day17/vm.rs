@@ -1,32 +1,221 @@
+use crate::register::Register;
+use rayon::prelude::*;
+use std::fmt;
 use std::fs;
 
-#[derive(Debug)]
-pub struct VM {
-    instruction_pointer: usize,
-    reg_a: u64,
-    reg_b: u64,
-    reg_c: u64,
+// Enough for any of the puzzle programs many times over
+pub(crate) const DEFAULT_MAX_STEPS: usize = 1_000_000;
 
-    program: Vec<usize>,
-    output: Vec<usize>,
+// Registers are u64 unless asked otherwise, see from_input
+#[derive(Debug, Clone)]
+pub struct VM<R = u64> {
+    pub(crate) instruction_pointer: usize,
+    pub(crate) reg_a: R,
+    pub(crate) reg_b: R,
+    pub(crate) reg_c: R,
+
+    pub(crate) program: Vec<usize>,
+    pub(crate) output: Vec<usize>,
+
+    // run gives up with VmError::StepLimit after this many instructions
+    pub(crate) max_steps: usize,
+}
+
+// Why a program couldn't run to the end. ip is where the instruction is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VmError {
+    // an opcode in the last cell, from an odd length program or a jump there
+    MissingOperand { ip: usize },
+    UnknownOpcode { ip: usize, opcode: usize },
+    // combo operand 7
+    ReservedCombo { ip: usize },
+    StepLimit(usize),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::MissingOperand { ip } => {
+                write!(f, "ip {}: instruction has no operand after it", ip)
+            }
+            VmError::UnknownOpcode { ip, opcode } => {
+                write!(f, "ip {}: unknown opcode {}", ip, opcode)
+            }
+            VmError::ReservedCombo { ip } => {
+                write!(f, "ip {}: combo operand 7 is reserved", ip)
+            }
+            VmError::StepLimit(steps) => {
+                write!(f, "still running after {} steps", steps)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+// What a program printed, shown comma separated as the puzzle wants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Output(pub(crate) Vec<usize>);
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self.0.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", values.join(","))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Registers<R = u64> {
+    pub(crate) a: R,
+    pub(crate) b: R,
+    pub(crate) c: R,
+}
+
+// One executed instruction, with the registers either side of it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Step<R = u64> {
+    pub(crate) ip: usize,
+    pub(crate) instruction: usize,
+    pub(crate) operand: usize,
+    pub(crate) before: Registers<R>,
+    pub(crate) after: Registers<R>,
+    pub(crate) output: Option<usize>,
+}
+
+impl<R: Register> Step<R> {
+    // (register, before, after) for each register the instruction changed
+    pub(crate) fn deltas(&self) -> Vec<(char, R, R)> {
+        [
+            ('A', self.before.a.clone(), self.after.a.clone()),
+            ('B', self.before.b.clone(), self.after.b.clone()),
+            ('C', self.before.c.clone(), self.after.c.clone()),
+        ]
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .collect()
+    }
+}
+
+// The eight 3-bit instructions, in opcode order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Opcode {
+    Adv,
+    Bxl,
+    Bst,
+    Jnz,
+    Bxc,
+    Out,
+    Bdv,
+    Cdv,
+}
+
+// How an instruction reads its operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandKind {
+    // 0-3 as is, 4-6 registers A, B, C, 7 reserved
+    Combo,
+    Literal,
+    // literal, but an instruction address
+    Jump,
+    // read but not used
+    Ignored,
+}
+
+impl Opcode {
+    pub(crate) const ALL: [Opcode; 8] = [
+        Opcode::Adv,
+        Opcode::Bxl,
+        Opcode::Bst,
+        Opcode::Jnz,
+        Opcode::Bxc,
+        Opcode::Out,
+        Opcode::Bdv,
+        Opcode::Cdv,
+    ];
+
+    pub(crate) fn from_code(code: usize) -> Option<Opcode> {
+        Opcode::ALL.get(code).copied()
+    }
+
+    pub(crate) fn code(&self) -> usize {
+        *self as usize
+    }
+
+    pub(crate) fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::Adv => "adv",
+            Opcode::Bxl => "bxl",
+            Opcode::Bst => "bst",
+            Opcode::Jnz => "jnz",
+            Opcode::Bxc => "bxc",
+            Opcode::Out => "out",
+            Opcode::Bdv => "bdv",
+            Opcode::Cdv => "cdv",
+        }
+    }
+
+    pub(crate) fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        Opcode::ALL
+            .iter()
+            .find(|o| o.mnemonic() == mnemonic)
+            .copied()
+    }
+
+    pub(crate) fn operand_kind(&self) -> OperandKind {
+        match self {
+            Opcode::Adv | Opcode::Bst | Opcode::Out | Opcode::Bdv | Opcode::Cdv => {
+                OperandKind::Combo
+            }
+            Opcode::Bxl => OperandKind::Literal,
+            Opcode::Jnz => OperandKind::Jump,
+            Opcode::Bxc => OperandKind::Ignored,
+        }
+    }
 }
 
 impl VM {
     pub(crate) fn new(file: &str) -> Self {
-        let mut reg_a = 0;
-        let mut reg_b = 0;
-        let mut reg_c = 0;
+        Self::from_file(file)
+    }
+
+    pub(crate) fn parse(contents: &str) -> Self {
+        Self::from_input(contents)
+    }
+}
+
+impl<R: Register> VM<R> {
+    // As new and parse, for the wider registers, e.g. VM::<BigUint>::from_file(..)
+    pub(crate) fn from_file(file: &str) -> Self {
+        Self::from_input(&fs::read_to_string(file).expect("Can't read the file"))
+    }
+
+    // Register A/B/C and Program lines, as in the puzzle input
+    pub(crate) fn from_input(contents: &str) -> Self {
+        let mut reg_a = R::from_u64(0);
+        let mut reg_b = R::from_u64(0);
+        let mut reg_c = R::from_u64(0);
         let mut program: Vec<usize> = Vec::new();
-        for row in fs::read_to_string(file)
-            .expect("Can't read the file")
-            .lines()
-        {
+        let register = |value: &str| {
+            value
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Bad register value {:?}", value.trim()))
+        };
+        for row in contents.lines() {
             let r: Vec<&str> = row.split(':').collect();
             match r[0] {
-                "Register A" => reg_a = r[1].trim().parse().unwrap(),
-                "Register B" => reg_b = r[1].trim().parse().unwrap(),
-                "Register C" => reg_c = r[1].trim().parse().unwrap(),
-                "Program" => program = r[1].trim().split(',').map(|o| o.parse().unwrap()).collect(),
+                "Register A" => reg_a = register(r[1]),
+                "Register B" => reg_b = register(r[1]),
+                "Register C" => reg_c = register(r[1]),
+                // An empty list is an empty program
+                "Program" => {
+                    program = r[1]
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|o| !o.is_empty())
+                        .map(|o| o.parse().unwrap())
+                        .collect()
+                }
                 _ => {}
             }
         }
@@ -39,53 +228,109 @@ impl VM {
             reg_c,
             program,
             output,
+            max_steps: DEFAULT_MAX_STEPS,
         }
     }
 
-    pub(crate) fn run(&mut self) -> String {
-        while self.instruction_pointer < self.program.len() {
-            // dbg!(&self);
-            let instruction = self.program[self.instruction_pointer];
-            let operand = self.program[self.instruction_pointer + 1];
-            match instruction {
-                0 => self.adv(operand),
-                1 => self.bxl(operand),
-                2 => self.bst(operand),
-                3 => self.jnz(operand),
-                4 => self.bxc(operand),
-                5 => self.out(operand),
-                6 => self.bdv(operand),
-                7 => self.cdv(operand),
-                _ => (),
-            }
+    // Back to the puzzle input format that parse reads
+    pub(crate) fn to_file_format(&self) -> String {
+        format!(
+            "Register A: {}\nRegister B: {}\nRegister C: {}\n\nProgram: {}\n",
+            self.reg_a,
+            self.reg_b,
+            self.reg_c,
+            self.program
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+
+    pub(crate) fn run(&mut self) -> Result<Output, VmError> {
+        for value in self.outputs() {
+            value?;
+        }
+        Ok(Output(self.output.clone()))
+    }
+
+    // Each value as the program prints it, running only as far as needed to get the
+    // next one. An error ends the iterator after it's been returned.
+    pub(crate) fn outputs(&mut self) -> Outputs<'_, R> {
+        Outputs {
+            vm: self,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    pub(crate) fn registers(&self) -> Registers<R> {
+        Registers {
+            a: self.reg_a.clone(),
+            b: self.reg_b.clone(),
+            c: self.reg_c.clone(),
         }
-        self.output.iter().map(|x| x.to_string() + ",")
-            .collect::<String>()
-            .trim_end_matches(',')
-            .to_string()
     }
 
-    fn adv(&mut self, combo: usize) {
-        if let Some(o) = self.combo_value(combo) {
-            self.reg_a = self.reg_a / 2u64.pow(o as u32);
+    // Execute the instruction at the instruction pointer, None once halted. On an
+    // error nothing has changed and the instruction pointer stays put.
+    pub(crate) fn step(&mut self) -> Result<Option<Step<R>>, VmError> {
+        if self.instruction_pointer >= self.program.len() {
+            return Ok(None);
         }
+        let ip = self.instruction_pointer;
+        let before = self.registers();
+        let outputs = self.output.len();
+        let instruction = self.program[ip];
+        let operand = *self
+            .program
+            .get(ip + 1)
+            .ok_or(VmError::MissingOperand { ip })?;
+        match instruction {
+            0 => self.adv(operand)?,
+            1 => self.bxl(operand),
+            2 => self.bst(operand)?,
+            3 => self.jnz(operand),
+            4 => self.bxc(operand),
+            5 => self.out(operand)?,
+            6 => self.bdv(operand)?,
+            7 => self.cdv(operand)?,
+            _ => {
+                return Err(VmError::UnknownOpcode {
+                    ip,
+                    opcode: instruction,
+                })
+            }
+        }
+        Ok(Some(Step {
+            ip,
+            instruction,
+            operand,
+            before,
+            after: self.registers(),
+            output: self.output.get(outputs).copied(),
+        }))
+    }
+
+    fn adv(&mut self, combo: usize) -> Result<(), VmError> {
+        self.reg_a = self.shift_a(combo)?;
         self.instruction_pointer += 2;
+        Ok(())
     }
 
     fn bxl(&mut self, literal: usize) { 
-        self.reg_b = self.reg_b ^ literal as u64;
+        self.reg_b = self.reg_b.xor(&R::from_u64(literal as u64));
         self.instruction_pointer += 2;
     }
 
-    fn bst(&mut self, combo: usize) {
-        if let Some(o) = self.combo_value(combo) {
-            self.reg_b = o.rem_euclid(8);
-        }
+    fn bst(&mut self, combo: usize) -> Result<(), VmError> {
+        self.reg_b = R::from_u64(self.combo_value(combo)?.low_digit() as u64);
         self.instruction_pointer += 2;
+        Ok(())
     }
 
     fn jnz(&mut self, literal: usize) {
-        if self.reg_a != 0 {
+        if !self.reg_a.is_zero() {
             self.instruction_pointer = literal;
         } else {
             self.instruction_pointer += 2;
@@ -93,68 +338,141 @@ impl VM {
     }
 
     fn bxc(&mut self, _operand: usize) {
-        self.reg_b = self.reg_b ^ self.reg_c;
+        self.reg_b = self.reg_b.xor(&self.reg_c);
         self.instruction_pointer += 2;
     }
 
-    fn out(&mut self, combo: usize) {
-        // println!("      A : {:o}", self.reg_a);
-        if let Some(o) = self.combo_value(combo) {
-            self.output.push(o.rem_euclid(8) as usize);
-        }
+    fn out(&mut self, combo: usize) -> Result<(), VmError> {
+        let o = self.combo_value(combo)?;
+        self.output.push(o.low_digit());
         self.instruction_pointer += 2;
+        Ok(())
     }
 
-    fn bdv(&mut self, combo: usize) {
-        if let Some(o) = self.combo_value(combo) {
-            self.reg_b = self.reg_a / 2u64.pow(o as u32);
-        }
+    fn bdv(&mut self, combo: usize) -> Result<(), VmError> {
+        self.reg_b = self.shift_a(combo)?;
         self.instruction_pointer += 2;
+        Ok(())
     }
 
-    fn cdv(&mut self, combo: usize) {
-        if let Some(o) = self.combo_value(combo) {
-            self.reg_c = self.reg_a / 2u64.pow(o as u32);
-        }
+    fn cdv(&mut self, combo: usize) -> Result<(), VmError> {
+        self.reg_c = self.shift_a(combo)?;
         self.instruction_pointer += 2;
+        Ok(())
     }
     
-    fn combo_value(&mut self, combo: usize) -> Option<u64> {
+    fn combo_value(&self, combo: usize) -> Result<R, VmError> {
         match combo {
-            0..=3 => Some(combo as u64),
-            4 => Some(self.reg_a),
-            5 => Some(self.reg_b),
-            6 => Some(self.reg_c),
-            _ => None,
+            0..=3 => Ok(R::from_u64(combo as u64)),
+            4 => Ok(self.reg_a.clone()),
+            5 => Ok(self.reg_b.clone()),
+            6 => Ok(self.reg_c.clone()),
+            _ => Err(VmError::ReservedCombo {
+                ip: self.instruction_pointer,
+            }),
         }
     }
+
+    // A / 2^combo, for adv, bdv and cdv
+    fn shift_a(&self, combo: usize) -> Result<R, VmError> {
+        Ok(self.reg_a.shift_right(&self.combo_value(combo)?))
+    }
     
+    // Start again from the top with these registers
+    pub(crate) fn reset(&mut self, reg_a: R, reg_b: R, reg_c: R) {
+        self.reg_a = reg_a;
+        self.reg_b = reg_b;
+        self.reg_c = reg_c;
+        self.instruction_pointer = 0;
+        self.output.clear();
+    }
+
+    // Whether the program prints exactly `expected` from where it is now. Gives up
+    // at the first value that doesn't match rather than running to the end.
+    pub(crate) fn prints(&mut self, expected: &[usize]) -> bool {
+        let mut count = 0;
+        for value in self.outputs() {
+            match value {
+                Ok(v) if expected.get(count) == Some(&v) => count += 1,
+                _ => return false,
+            }
+        }
+        count == expected.len()
+    }
+
+    pub(crate) fn prints_itself(&mut self) -> bool {
+        let program = self.program.clone();
+        self.prints(&program)
+    }
+}
+
+impl VM {
     // The obligatory doomed brute-force approach
     pub(crate) fn repeat_run(&mut self, lower: u64, upper: u64) -> Option<u64> {
         // Save initial state for rerun
         let reg_b = self.reg_b;
         let reg_c = self.reg_c;
-    
+        let program = self.program.clone();
+
         for reg_a in lower..upper {
-            self.reg_a = reg_a;
-            self.reg_b = reg_b;
-            self.reg_c = reg_c;
-            self.instruction_pointer = 0;
-            self.output.clear();
-            _ = self.run();
-            // println!("program : {:?}", self.program);
-            // println!(" output : {:?}\n", self.output);
-            if self.program == self.output {
+            self.reset(reg_a, reg_b, reg_c);
+            if self.prints(&program) {
                 return Some(reg_a);
             }
         }
         None
     }
-    
-    fn reconstruct(&mut self) -> Option<u64> {
-        
-        
-        
-        Some(123)
+
+    // repeat_run spread over rayon's threads. The VM, and the program it has to
+    // print, are cloned once per rayon job rather than for every A tried. Still
+    // the smallest A in the range.
+    pub(crate) fn par_repeat_run(&self, lower: u64, upper: u64) -> Option<u64> {
+        (lower..upper)
+            .into_par_iter()
+            .map_init(
+                || (self.clone(), self.program.clone()),
+                |(vm, program), reg_a| {
+                    vm.reset(reg_a, self.reg_b, self.reg_c);
+                    vm.prints(program).then_some(reg_a)
+                },
+            )
+            .find_first(Option::is_some)
+            .flatten()
+    }
+}
+
+pub(crate) struct Outputs<'a, R = u64> {
+    vm: &'a mut VM<R>,
+    // counted against vm.max_steps
+    steps: usize,
+    done: bool,
+}
+
+impl<R: Register> Iterator for Outputs<'_, R> {
+    type Item = Result<usize, VmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let step = match self.vm.step() {
+                Ok(Some(step)) => step,
+                Ok(None) => break,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            self.steps += 1;
+            if self.steps >= self.vm.max_steps
+                && self.vm.instruction_pointer < self.vm.program.len()
+            {
+                self.done = true;
+                return Some(Err(VmError::StepLimit(self.steps)));
+            }
+            if step.output.is_some() {
+                return step.output.map(Ok);
+            }
+        }
+        self.done = true;
+        None
     }
 }
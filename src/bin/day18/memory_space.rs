@@ -0,0 +1,95 @@
+use aocutils::point::Point;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::ops::Range;
+
+// Square memory grid with bytes falling onto it, one per nanosecond, in the order
+// given by the input file. Start top left, exit bottom right.
+
+#[derive(Debug)]
+pub struct MemorySpace {
+    bytes: Vec<Point<i32>>,
+    xrange: Range<i32>,
+    yrange: Range<i32>,
+    start: Point<i32>,
+    end: Point<i32>,
+}
+
+impl MemorySpace {
+    // size is the side of the grid, 7 for the example, 71 for the real data
+    pub(crate) fn new(file: &str, size: i32) -> Self {
+        let mut bytes = Vec::new();
+        for row in fs::read_to_string(file)
+            .expect("Can't read the file")
+            .lines()
+        {
+            if let Some((x, y)) = row.trim().split_once(',') {
+                bytes.push(Point {
+                    x: x.parse().unwrap(),
+                    y: y.parse().unwrap(),
+                });
+            }
+        }
+
+        Self {
+            bytes,
+            xrange: 0..size,
+            yrange: 0..size,
+            start: Point { x: 0, y: 0 },
+            end: Point {
+                x: size - 1,
+                y: size - 1,
+            },
+        }
+    }
+
+    // BFS from start to end, with the first `fallen` bytes corrupting the grid.
+    // Unit weight edges, so the first time we reach the end is the shortest.
+    pub(crate) fn shortest_path(&self, fallen: usize) -> Option<usize> {
+        let corrupted: HashSet<Point<i32>> = self.bytes.iter().take(fallen).copied().collect();
+        if corrupted.contains(&self.start) || corrupted.contains(&self.end) {
+            return None;
+        }
+
+        let mut seen = HashSet::from([self.start]);
+        let mut queue = VecDeque::from([(self.start, 0)]);
+        while let Some((pos, steps)) = queue.pop_front() {
+            if pos == self.end {
+                return Some(steps);
+            }
+            for next in pos.cardinal_points() {
+                if self.xrange.contains(&next.x)
+                    && self.yrange.contains(&next.y)
+                    && !corrupted.contains(&next)
+                    && seen.insert(next)
+                {
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+        // Exit not reachable
+        None
+    }
+
+    // Once the exit is cut off it stays cut off, so reachability is monotonic in the
+    // number of fallen bytes. Binary search for the first count with no path, rather
+    // than a BFS per byte. Returns the byte that completed the blockage.
+    pub(crate) fn first_blocking_byte(&self) -> Option<Point<i32>> {
+        let mut lower = 0;
+        let mut upper = self.bytes.len();
+        if self.shortest_path(upper).is_some() {
+            // Never blocked, even with every byte down
+            return None;
+        }
+        // Invariant: path exists after `lower` bytes, not after `upper` bytes
+        while upper - lower > 1 {
+            let mid = lower + (upper - lower) / 2;
+            if self.shortest_path(mid).is_some() {
+                lower = mid;
+            } else {
+                upper = mid;
+            }
+        }
+        Some(self.bytes[upper - 1])
+    }
+}